@@ -13,6 +13,10 @@ fn generate_bindings() {
     // === Compile the wrapper.c ===
     let mut build = cc::Build::new();
     build.file("native/wrapper.c");
+    let builtins = std::env::var("CARGO_FEATURE_BUILTINS").is_ok();
+    if builtins {
+        build.define("WRAPPEDVIZ_BUILTINS", None);
+    }
 
     for flag in cflags.split_whitespace() {
         if flag.starts_with("-I") {
@@ -34,6 +38,20 @@ fn generate_bindings() {
     println!("cargo:rustc-link-lib=dylib=m");
     println!("cargo:rustc-link-lib=dylib=expat");
 
+    // === Link the plugins `Context::with_builtins` registers ===
+    if builtins {
+        let libdir_output = Command::new("pkg-config")
+            .args(&["--variable=libdir", "libgvc"])
+            .output()
+            .expect("Failed to run pkg-config --variable=libdir for libgvc");
+        let plugin_dir = std::path::PathBuf::from(String::from_utf8_lossy(&libdir_output.stdout).trim()).join("graphviz");
+        println!("cargo:rustc-link-search=native={}", plugin_dir.display());
+        println!("cargo:rustc-link-arg=-Wl,-rpath,{}", plugin_dir.display());
+        for plugin in &["gvplugin_core", "gvplugin_dot_layout", "gvplugin_neato_layout"] {
+            link_plugin(&plugin_dir, plugin);
+        }
+    }
+
     // === Generate Rust bindings ===
     let mut bindings = bindgen::Builder::default()
         .header("native/wrapper.h");
@@ -59,6 +77,21 @@ fn generate_bindings() {
         .expect("Couldn't write bindings!");
 }
 
+/// links a graphviz plugin library, distributions usually only ship the versioned `libname.so.N`
+#[cfg(all(feature = "bindings", not(target_arch = "wasm32")))]
+fn link_plugin(dir: &std::path::Path, name: &str) {
+    let prefix = format!("lib{}.", name);
+    let file = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|file| file.starts_with(&prefix) && !file.ends_with(".la"))
+        .min_by_key(|file| file.len())
+        .unwrap_or_else(|| panic!("{} not found in {}, needed by the `builtins` feature", name, dir.display()));
+    println!("cargo:rustc-link-lib=dylib:+verbatim={}", file);
+}
+
 fn main() {
     let target = std::env::var("TARGET").expect("TARGET not set by cargo").trim().to_string();
     let bindings_enabled = std::env::var("CARGO_FEATURE_BINDINGS").is_ok();
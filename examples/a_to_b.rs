@@ -6,7 +6,7 @@ use wrappedviz::cgraph::{Context, Graph};
 fn main() {
     let ctx = Context::new();
     // Start with an empty directed graph
-    let mut graph = Graph::new("digraph G {}", &ctx);
+    let mut graph = Graph::parse("digraph G {}", &ctx).unwrap();
     // Add nodes A and B
     graph.add_node("A");
     graph.add_node("B");
@@ -174,6 +174,7 @@
               pkgs.libxml2
               pkgs.expat
               pkgs.llvmPackages.libclang
+              pkgs.valgrind
             ];
 
             shellHook = ''
@@ -1,10 +1,261 @@
+#define _GNU_SOURCE
+#include <stdlib.h>
+#include <stdio.h>
+#include <string.h>
+#include <gvplugin_render.h>
+#include <gvplugin_device.h>
+#include <gvio.h>
 #include "wrapper.h"
 
 extern gvplugin_library_t gvplugin_dot_layout_LTX_library;
 
+#ifdef WRAPPEDVIZ_BUILTINS
+extern gvplugin_library_t gvplugin_core_LTX_library;
+extern gvplugin_library_t gvplugin_neato_layout_LTX_library;
+
+lt_symlist_t rust_builtins[] = {
+    {"gvplugin_core_LTX_library", &gvplugin_core_LTX_library},
+    {"gvplugin_dot_layout_LTX_library", &gvplugin_dot_layout_LTX_library},
+    {"gvplugin_neato_layout_LTX_library", &gvplugin_neato_layout_LTX_library},
+    {0, 0},
+};
+#endif
+
 Agnode_t *rust_aghead(Agedge_t * ptr) {
     return aghead(ptr);
 }
 Agnode_t *rust_agtail(Agedge_t * ptr) {
     return agtail(ptr);
-}
\ No newline at end of file
+}
+Agedge_t *rust_agmkout(Agedge_t * ptr) {
+    return AGMKOUT(ptr);
+}
+
+struct rust_writer {
+    void *cookie;
+    rust_write_fn write;
+};
+
+static int rust_writer_close(void *c) {
+    free(c);
+    return 0;
+}
+
+#if defined(__GLIBC__)
+static ssize_t rust_writer_write(void *c, const char *buf, size_t size) {
+    struct rust_writer *w = c;
+    return w->write(w->cookie, buf, size);
+}
+
+FILE *rust_open_writer(void *cookie, rust_write_fn write) {
+    struct rust_writer *w = malloc(sizeof(*w));
+    if (w == NULL) {
+        return NULL;
+    }
+    w->cookie = cookie;
+    w->write = write;
+    cookie_io_functions_t funcs = {
+        .read = NULL,
+        .write = rust_writer_write,
+        .seek = NULL,
+        .close = rust_writer_close,
+    };
+    FILE *f = fopencookie(w, "w", funcs);
+    if (f == NULL) {
+        free(w);
+    }
+    return f;
+}
+#elif defined(__APPLE__) || defined(__FreeBSD__) || defined(__NetBSD__) || defined(__OpenBSD__)
+static int rust_writer_write(void *c, const char *buf, int size) {
+    struct rust_writer *w = c;
+    return (int)w->write(w->cookie, buf, (size_t)size);
+}
+
+FILE *rust_open_writer(void *cookie, rust_write_fn write) {
+    struct rust_writer *w = malloc(sizeof(*w));
+    if (w == NULL) {
+        return NULL;
+    }
+    w->cookie = cookie;
+    w->write = write;
+    FILE *f = funopen(w, NULL, rust_writer_write, NULL, rust_writer_close);
+    if (f == NULL) {
+        free(w);
+    }
+    return f;
+}
+#else
+FILE *rust_open_writer(void *cookie, rust_write_fn write) {
+    (void)cookie;
+    (void)write;
+    return NULL;
+}
+#endif
+
+/* everything graphviz needs to find a rust renderer, the engine comes first so
+ * the engine pointer graphviz hands to the callbacks leads back to the rest */
+struct rust_renderer {
+    gvrender_engine_t engine;
+    void *renderer;
+    rust_render_ops ops;
+    char *format;
+    char *device_type;
+    gvrender_features_t render_features;
+    gvdevice_features_t device_features;
+    gvplugin_installed_t render_types[2];
+    gvplugin_installed_t device_types[2];
+    gvplugin_api_t apis[3];
+    gvplugin_library_t library;
+};
+
+static struct rust_renderer *renderer_of(GVJ_t *job) {
+    return (struct rust_renderer *)job->render.engine;
+}
+
+static rust_render_style style_of(GVJ_t *job) {
+    obj_state_t *obj = job->obj;
+    rust_render_style style;
+    memcpy(style.pencolor, obj->pencolor.u.rgba, 4);
+    memcpy(style.fillcolor, obj->fillcolor.u.rgba, 4);
+    style.penwidth = obj->penwidth;
+    style.pen = obj->pen;
+    return style;
+}
+
+static void rust_begin_graph(GVJ_t *job) {
+    struct rust_renderer *r = renderer_of(job);
+    graph_t *g = job->obj->u.g;
+    r->ops.begin_graph(r->renderer, job, agnameof(g), GD_bb(g));
+}
+
+static void rust_end_graph(GVJ_t *job) {
+    struct rust_renderer *r = renderer_of(job);
+    r->ops.end_graph(r->renderer, job);
+}
+
+static void rust_begin_cluster(GVJ_t *job) {
+    struct rust_renderer *r = renderer_of(job);
+    r->ops.begin_cluster(r->renderer, job, agnameof(job->obj->u.sg));
+}
+
+static void rust_end_cluster(GVJ_t *job) {
+    struct rust_renderer *r = renderer_of(job);
+    r->ops.end_cluster(r->renderer, job);
+}
+
+static void rust_begin_node(GVJ_t *job) {
+    struct rust_renderer *r = renderer_of(job);
+    r->ops.begin_node(r->renderer, job, agnameof(job->obj->u.n));
+}
+
+static void rust_end_node(GVJ_t *job) {
+    struct rust_renderer *r = renderer_of(job);
+    r->ops.end_node(r->renderer, job);
+}
+
+static void rust_begin_edge(GVJ_t *job) {
+    struct rust_renderer *r = renderer_of(job);
+    Agedge_t *e = job->obj->u.e;
+    r->ops.begin_edge(r->renderer, job, agnameof(agtail(e)), agnameof(aghead(e)));
+}
+
+static void rust_end_edge(GVJ_t *job) {
+    struct rust_renderer *r = renderer_of(job);
+    r->ops.end_edge(r->renderer, job);
+}
+
+static void rust_ellipse(GVJ_t *job, pointf *A, int filled) {
+    struct rust_renderer *r = renderer_of(job);
+    rust_render_style style = style_of(job);
+    r->ops.ellipse(r->renderer, job, &style, A, filled);
+}
+
+static void rust_polygon(GVJ_t *job, pointf *A, size_t n, int filled) {
+    struct rust_renderer *r = renderer_of(job);
+    rust_render_style style = style_of(job);
+    r->ops.polygon(r->renderer, job, &style, A, n, filled);
+}
+
+static void rust_beziercurve(GVJ_t *job, pointf *A, size_t n, int filled) {
+    struct rust_renderer *r = renderer_of(job);
+    rust_render_style style = style_of(job);
+    r->ops.beziercurve(r->renderer, job, &style, A, n, filled);
+}
+
+static void rust_polyline(GVJ_t *job, pointf *A, size_t n) {
+    struct rust_renderer *r = renderer_of(job);
+    rust_render_style style = style_of(job);
+    r->ops.polyline(r->renderer, job, &style, A, n);
+}
+
+static void rust_textspan(GVJ_t *job, pointf p, textspan_t *span) {
+    struct rust_renderer *r = renderer_of(job);
+    const char *font = span->font && span->font->name ? span->font->name : "";
+    double size = span->font ? span->font->size : 0.;
+    r->ops.textspan(r->renderer, job, p, span->str, font, size, job->obj->pencolor.u.rgba, span->just);
+}
+
+void *rust_add_renderer(GVC_t *gvc, const char *format, void *renderer, const rust_render_ops *ops) {
+    struct rust_renderer *r = calloc(1, sizeof(*r));
+    if (r == NULL) {
+        return NULL;
+    }
+    size_t len = strlen(format);
+    r->format = strdup(format);
+    r->device_type = malloc(2 * len + 2);
+    if (r->format == NULL || r->device_type == NULL) {
+        rust_free_renderer(r);
+        return NULL;
+    }
+    /* "format:format", the device depends on the renderer of the same name */
+    snprintf(r->device_type, 2 * len + 2, "%s:%s", format, format);
+
+    r->renderer = renderer;
+    r->ops = *ops;
+    r->engine = (gvrender_engine_t){
+        .begin_graph = rust_begin_graph,
+        .end_graph = rust_end_graph,
+        .begin_cluster = rust_begin_cluster,
+        .end_cluster = rust_end_cluster,
+        .begin_node = rust_begin_node,
+        .end_node = rust_end_node,
+        .begin_edge = rust_begin_edge,
+        .end_edge = rust_end_edge,
+        .textspan = rust_textspan,
+        .ellipse = rust_ellipse,
+        .polygon = rust_polygon,
+        .beziercurve = rust_beziercurve,
+        .polyline = rust_polyline,
+    };
+    r->render_features = (gvrender_features_t){
+        .flags = GVRENDER_DOES_TRANSFORM,
+        .default_pad = 4.,
+        .color_type = RGBA_BYTE,
+    };
+    r->device_features = (gvdevice_features_t){
+        .flags = GVDEVICE_DOES_TRUECOLOR,
+        .default_dpi = {72., 72.},
+    };
+    r->render_types[0] = (gvplugin_installed_t){0, r->format, 1, &r->engine, &r->render_features};
+    r->device_types[0] = (gvplugin_installed_t){0, r->device_type, 1, NULL, &r->device_features};
+    r->apis[0] = (gvplugin_api_t){API_render, r->render_types};
+    r->apis[1] = (gvplugin_api_t){API_device, r->device_types};
+    r->library = (gvplugin_library_t){r->format, r->apis};
+    gvAddLibrary(gvc, &r->library);
+    return r;
+}
+
+void rust_free_renderer(void *handle) {
+    struct rust_renderer *r = handle;
+    if (r == NULL) {
+        return;
+    }
+    free(r->format);
+    free(r->device_type);
+    free(r);
+}
+
+size_t rust_render_write(GVJ_t *job, const char *buf, size_t len) {
+    return gvwrite(job, buf, len);
+}
@@ -1,7 +1,61 @@
+#include <stdio.h>
+#include <sys/types.h>
 #include <gvc.h>
 #include <cgraph.h>
 
 extern gvplugin_library_t gvplugin_dot_layout_LTX_library;
 
+/* the core, dot_layout and neato_layout plugins for gvContextPlugins,
+ * only defined when built with WRAPPEDVIZ_BUILTINS */
+extern lt_symlist_t rust_builtins[];
+
 extern Agnode_t *rust_aghead(Agedge_t * ptr);
-extern Agnode_t *rust_agtail(Agedge_t * ptr);
\ No newline at end of file
+extern Agnode_t *rust_agtail(Agedge_t * ptr);
+/* the out-edge half of an edge, the half agnameof and the edge maps use */
+extern Agedge_t *rust_agmkout(Agedge_t * ptr);
+
+/* called with every chunk written to a stream from rust_open_writer,
+ * returns the number of bytes consumed or -1 on error */
+typedef ssize_t (*rust_write_fn)(void *cookie, const char *buf, size_t len);
+
+/* opens a write only FILE that forwards all output to `write`,
+ * returns NULL where the platform has no custom streams */
+extern FILE *rust_open_writer(void *cookie, rust_write_fn write);
+
+/* the pen and fill of the shape being drawn, colors are rgba */
+typedef struct rust_render_style {
+    unsigned char pencolor[4];
+    unsigned char fillcolor[4];
+    double penwidth;
+    /* 0 none, 1 dashed, 2 dotted, 3 solid, like graphviz's pen_type */
+    int pen;
+} rust_render_style;
+
+/* callbacks of a renderer written in rust. `renderer` is the pointer given to
+ * rust_add_renderer, `job` is passed back to rust_render_write. Points are in
+ * graph units (points, y up), ellipses are given as center and corner. */
+typedef struct rust_render_ops {
+    void (*begin_graph)(void *renderer, GVJ_t *job, const char *name, boxf bb);
+    void (*end_graph)(void *renderer, GVJ_t *job);
+    void (*begin_cluster)(void *renderer, GVJ_t *job, const char *name);
+    void (*end_cluster)(void *renderer, GVJ_t *job);
+    void (*begin_node)(void *renderer, GVJ_t *job, const char *name);
+    void (*end_node)(void *renderer, GVJ_t *job);
+    void (*begin_edge)(void *renderer, GVJ_t *job, const char *tail, const char *head);
+    void (*end_edge)(void *renderer, GVJ_t *job);
+    void (*ellipse)(void *renderer, GVJ_t *job, const rust_render_style *style, const pointf *points, int filled);
+    void (*polygon)(void *renderer, GVJ_t *job, const rust_render_style *style, const pointf *points, size_t n, int filled);
+    void (*beziercurve)(void *renderer, GVJ_t *job, const rust_render_style *style, const pointf *points, size_t n, int filled);
+    void (*polyline)(void *renderer, GVJ_t *job, const rust_render_style *style, const pointf *points, size_t n);
+    void (*textspan)(void *renderer, GVJ_t *job, pointf p, const char *text, const char *font, double size, const unsigned char *color, char just);
+} rust_render_ops;
+
+/* registers a render and a device plugin for `format` on `gvc` that forward to `ops`,
+ * returns a handle for rust_free_renderer or NULL if out of memory */
+extern void *rust_add_renderer(GVC_t *gvc, const char *format, void *renderer, const rust_render_ops *ops);
+
+/* frees a handle from rust_add_renderer, only once `gvc` is freed */
+extern void rust_free_renderer(void *handle);
+
+/* writes to the output of a render job, returns the number of bytes written */
+extern size_t rust_render_write(GVJ_t *job, const char *buf, size_t len);
@@ -8362,12 +8362,147 @@ unsafe extern "C" {
 unsafe extern "C" {
     pub static mut gvplugin_dot_layout_LTX_library: gvplugin_library_t;
 }
+unsafe extern "C" {
+    pub static mut rust_builtins: [lt_symlist_t; 0usize];
+}
 unsafe extern "C" {
     pub fn rust_aghead(ptr: *mut Agedge_t) -> *mut Agnode_t;
 }
 unsafe extern "C" {
     pub fn rust_agtail(ptr: *mut Agedge_t) -> *mut Agnode_t;
 }
+unsafe extern "C" {
+    pub fn rust_agmkout(ptr: *mut Agedge_t) -> *mut Agedge_t;
+}
+pub type ssize_t = __ssize_t;
+pub type rust_write_fn = ::std::option::Option<
+    unsafe extern "C" fn(
+        cookie: *mut ::std::os::raw::c_void,
+        buf: *const ::std::os::raw::c_char,
+        len: usize,
+    ) -> ssize_t,
+>;
+unsafe extern "C" {
+    pub fn rust_open_writer(cookie: *mut ::std::os::raw::c_void, write: rust_write_fn) -> *mut FILE;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rust_render_style {
+    pub pencolor: [::std::os::raw::c_uchar; 4usize],
+    pub fillcolor: [::std::os::raw::c_uchar; 4usize],
+    pub penwidth: f64,
+    pub pen: ::std::os::raw::c_int,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rust_render_ops {
+    pub begin_graph: ::std::option::Option<
+        unsafe extern "C" fn(
+            renderer: *mut ::std::os::raw::c_void,
+            job: *mut GVJ_t,
+            name: *const ::std::os::raw::c_char,
+            bb: boxf,
+        ),
+    >,
+    pub end_graph: ::std::option::Option<
+        unsafe extern "C" fn(renderer: *mut ::std::os::raw::c_void, job: *mut GVJ_t),
+    >,
+    pub begin_cluster: ::std::option::Option<
+        unsafe extern "C" fn(
+            renderer: *mut ::std::os::raw::c_void,
+            job: *mut GVJ_t,
+            name: *const ::std::os::raw::c_char,
+        ),
+    >,
+    pub end_cluster: ::std::option::Option<
+        unsafe extern "C" fn(renderer: *mut ::std::os::raw::c_void, job: *mut GVJ_t),
+    >,
+    pub begin_node: ::std::option::Option<
+        unsafe extern "C" fn(
+            renderer: *mut ::std::os::raw::c_void,
+            job: *mut GVJ_t,
+            name: *const ::std::os::raw::c_char,
+        ),
+    >,
+    pub end_node: ::std::option::Option<
+        unsafe extern "C" fn(renderer: *mut ::std::os::raw::c_void, job: *mut GVJ_t),
+    >,
+    pub begin_edge: ::std::option::Option<
+        unsafe extern "C" fn(
+            renderer: *mut ::std::os::raw::c_void,
+            job: *mut GVJ_t,
+            tail: *const ::std::os::raw::c_char,
+            head: *const ::std::os::raw::c_char,
+        ),
+    >,
+    pub end_edge: ::std::option::Option<
+        unsafe extern "C" fn(renderer: *mut ::std::os::raw::c_void, job: *mut GVJ_t),
+    >,
+    pub ellipse: ::std::option::Option<
+        unsafe extern "C" fn(
+            renderer: *mut ::std::os::raw::c_void,
+            job: *mut GVJ_t,
+            style: *const rust_render_style,
+            points: *const pointf,
+            filled: ::std::os::raw::c_int,
+        ),
+    >,
+    pub polygon: ::std::option::Option<
+        unsafe extern "C" fn(
+            renderer: *mut ::std::os::raw::c_void,
+            job: *mut GVJ_t,
+            style: *const rust_render_style,
+            points: *const pointf,
+            n: usize,
+            filled: ::std::os::raw::c_int,
+        ),
+    >,
+    pub beziercurve: ::std::option::Option<
+        unsafe extern "C" fn(
+            renderer: *mut ::std::os::raw::c_void,
+            job: *mut GVJ_t,
+            style: *const rust_render_style,
+            points: *const pointf,
+            n: usize,
+            filled: ::std::os::raw::c_int,
+        ),
+    >,
+    pub polyline: ::std::option::Option<
+        unsafe extern "C" fn(
+            renderer: *mut ::std::os::raw::c_void,
+            job: *mut GVJ_t,
+            style: *const rust_render_style,
+            points: *const pointf,
+            n: usize,
+        ),
+    >,
+    pub textspan: ::std::option::Option<
+        unsafe extern "C" fn(
+            renderer: *mut ::std::os::raw::c_void,
+            job: *mut GVJ_t,
+            p: pointf,
+            text: *const ::std::os::raw::c_char,
+            font: *const ::std::os::raw::c_char,
+            size: f64,
+            color: *const ::std::os::raw::c_uchar,
+            just: ::std::os::raw::c_char,
+        ),
+    >,
+}
+unsafe extern "C" {
+    pub fn rust_add_renderer(
+        gvc: *mut GVC_t,
+        format: *const ::std::os::raw::c_char,
+        renderer: *mut ::std::os::raw::c_void,
+        ops: *const rust_render_ops,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn rust_free_renderer(handle: *mut ::std::os::raw::c_void);
+}
+unsafe extern "C" {
+    pub fn rust_render_write(job: *mut GVJ_t, buf: *const ::std::os::raw::c_char, len: usize) -> usize;
+}
 pub type __builtin_va_list = [__va_list_tag; 1usize];
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
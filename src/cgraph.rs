@@ -1,16 +1,27 @@
 use crate::sys::*;
-use std::ffi::{CStr, CString, c_char};
-use crate::{Layout, OutputFormat};
-use crate::style::{Attribute, NodeAttr, EdgeAttr};
-
-use std::collections::HashMap;
+use std::ffi::{CStr, CString, c_char, c_int};
+use crate::{Diagnostic, Error, GraphKind, Layout, OutputFormat, PluginKind, Severity};
+use crate::layout::{BoundingBox, EdgeLayout, GraphLayout, LabelLayout, NodeLayout, Point, Spline};
+use crate::render::{Renderer, RendererSlot};
+use crate::rgraph::{self, RustGraph};
+use crate::style::html::HtmlLabel;
+use crate::style::{label_ports, Attribute, ClusterAttribute, CommonAttr, Port, EdgeAttr, EdgeAttribute, GraphAttr, NodeAttr, NodeAttribute};
+use crate::{CompatEdge, CompatGraph, CompatNode};
+use crate::theme::GraphTheme;
+use crate::trace::{debug, trace};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::Path;
 pub struct EdgeBuilder<'e, 'c, S: AsRef<str>> {
     edge_name: S,
     graph: &'e mut Graph<'c>,
 }
 
 impl<'e, 'c, S: AsRef<str>> EdgeBuilder<'e, 'c, S> {
-    pub fn set(&mut self, attr: EdgeAttr) -> Result<&mut Self, String> {
+    pub fn set(&mut self, attr: EdgeAttr) -> Result<&mut Self, Error> {
         self.graph
             .set_attr_on_edge(&self.edge_name.as_ref(), attr)?;
         Ok(self)
@@ -23,16 +34,31 @@ pub struct NodeBuilder<'n, 'c, S: AsRef<str>> {
 }
 
 impl<'n, 'c, S: AsRef<str>> NodeBuilder<'n, 'c, S> {
-    pub fn set(&mut self, attr: NodeAttr) -> Result<&mut Self, String> {
+    pub fn set(&mut self, attr: NodeAttr) -> Result<&mut Self, Error> {
         self.graph.set_attr_on_node(self.node_name.as_ref(), attr)?;
         Ok(self)
     }
 }
 
 /// an idiomatic wrapper around graphviz's `Agraph_t` type for interacting with graph structures
+///
+/// A graph borrows the [`Context`] it was created with and can't outlive it:
+///
+/// ```compile_fail
+/// use wrappedviz::cgraph::{Context, Graph};
+///
+/// let ctx = Context::new();
+/// let graph = Graph::parse("digraph G {}", &ctx).unwrap();
+/// drop(ctx);
+/// graph.name();
+/// ```
 pub struct Graph<'c> {
     graph: *mut Agraph_t,
+    /// the engine of the last layout, kept across `clear_layout`
     layout: Option<Layout>,
+    /// the engine `relayout` uses, set by `layout` and `apply_theme`
+    configured_layout: Option<Layout>,
+    layout_state: LayoutState,
     ctx: &'c Context,
     nodes: HashMap<String, Node>,
     edges: HashMap<String, Edge>,
@@ -44,41 +70,91 @@ impl<'c> Graph<'c> {
     /// # Arguments
     /// - `dot`: do struct diagram as str reference
     /// - `ctx`: Context used to create the graph see `Context`
+    /// # Panics
+    /// Panics if `dot` contains a NUL byte or is not valid DOT, see [`Graph::parse`].
     /// # Note
     /// graph cannot outlive `Context`
+    #[deprecated(note = "use `Graph::parse`, which reports malformed DOT instead of panicking")]
     pub fn new<S: AsRef<str>>(dot: S, ctx: &'c Context) -> Self {
-        let str_slice = dot.as_ref();
-        let c_dot = CString::new(str_slice).expect("DOT string contains null bytes");
+        Self::parse(dot, ctx).unwrap_or_else(|e| panic!("failed to parse DOT: {}", e))
+    }
+
+    /// Parses the first graph in `dot`.
+    ///
+    /// # Errors
+    /// - [`Error::InteriorNul`] if `dot` contains a NUL byte.
+    /// - [`Error::InvalidDot`] if graphviz could not parse `dot`, carrying the parser's messages
+    ///   (including the line number of syntax errors).
+    /// # Note
+    /// graph cannot outlive `Context`
+    pub fn parse<S: AsRef<str>>(dot: S, ctx: &'c Context) -> Result<Self, Error> {
+        let c_dot = CString::new(dot.as_ref())?;
 
-        let graph = unsafe { agmemread(c_dot.as_ptr()) };
+        let (graph, diagnostics) = ctx.capture(|| unsafe { agmemread(c_dot.as_ptr()) });
+        if graph.is_null() {
+            return Err(Error::InvalidDot(diagnostics));
+        }
+        Ok(Self::from_raw(graph, ctx))
+    }
+
+    /// Parses every graph in `dot`, for sources that contain several graphs one after another
+    /// like `digraph A { .. } digraph B { .. }`.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidDot`] if any of the graphs fails to parse.
+    pub fn parse_all<S: AsRef<str>>(dot: S, ctx: &'c Context) -> Result<Vec<Self>, Error> {
+        let dot = dot.as_ref();
+        if dot.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (raw_graphs, diagnostics) = ctx.capture(|| unsafe {
+            let file = libc::fmemopen(dot.as_ptr() as *mut libc::c_void, dot.len(), c"r".as_ptr());
+            if file.is_null() {
+                return Err(Error::Io(std::io::Error::last_os_error()));
+            }
+            // agread continues where the previous graph ended, and returns null at the end of input
+            let mut raw_graphs = Vec::new();
+            loop {
+                let graph = agread(file as *mut libc::c_void, std::ptr::null_mut());
+                if graph.is_null() {
+                    break;
+                }
+                raw_graphs.push(graph);
+            }
+            libc::fclose(file);
+            Ok(raw_graphs)
+        });
+
+        // wrap first so the graphs are closed again if a later one failed
+        let graphs: Vec<Self> = raw_graphs?
+            .into_iter()
+            .map(|graph| Self::from_raw(graph, ctx))
+            .collect();
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(Error::InvalidDot(diagnostics));
+        }
+        Ok(graphs)
+    }
 
+    /// wraps a graph read by graphviz, collecting its nodes and edges
+    fn from_raw(graph: *mut Agraph_t, ctx: &'c Context) -> Self {
         let mut nodes = HashMap::new();
         let mut edges = HashMap::new();
+        let edge_op = graph_kind(graph).edge_op();
 
-        println!("about to get nodes");
         unsafe {
             let mut n = agfstnode(graph);
             while !n.is_null() {
                 // Get node name
                 let name_ptr = agnameof(n as *mut _) as *const c_char;
                 let name = CStr::from_ptr(name_ptr).to_string_lossy().into_owned();
-                println!("about to try insert: {:?}", name);
                 nodes.insert(name.clone(), Node { raw: n });
 
                 // Iterate outgoing edges
                 let mut e = agfstout(graph, n);
                 while !e.is_null() {
-                    
-                    let tail = rust_agtail(e);  // source node pointer
-                    let head = rust_aghead(e);  // destination node pointer
-
-                    let tail_name_ptr = agnameof(tail as *mut _) as *const c_char;
-                    let head_name_ptr = agnameof(head as *mut _) as *const c_char;
-
-                    let tail_name = CStr::from_ptr(tail_name_ptr).to_string_lossy();
-                    let head_name = CStr::from_ptr(head_name_ptr).to_string_lossy();
-
-                    let edge_key = format!("{}->{}", tail_name, head_name);
+                    let edge_key = edge_key(e, edge_op, &edges);
                     edges.insert(edge_key, Edge { raw: e });
                     e = agnxtout(graph, e);
                 }
@@ -86,32 +162,109 @@ impl<'c> Graph<'c> {
                 n = agnxtnode(graph, n);
             }
         }
-        println!("about to return from Graph::new()");
-        Self {
+        let graph = Self {
             graph,
             layout: None,
+            configured_layout: None,
+            layout_state: LayoutState::None,
             ctx,
             nodes,
             edges,
+        };
+        debug!(
+            graph = %graph.name(),
+            nodes = graph.nodes.len(),
+            edges = graph.edges.len(),
+            "parsed graph"
+        );
+        graph
+    }
+
+    /// Returns the keys of all edges from `tail` to `head`, in the order they were created.
+    ///
+    /// In an undirected graph edges are matched in either direction. Parallel edges loaded
+    /// from DOT are stored under `tail->head`, `tail->head#2`, `tail->head#3` and so on,
+    /// unless they were named with the `key` attribute.
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if `tail` or `head` does not exist.
+    pub fn edges_between(&self, tail: &str, head: &str) -> Result<Vec<String>, Error> {
+        let tail = self.node_ptr(tail)?;
+        let head = self.node_ptr(head)?;
+        let directed = self.kind().is_directed();
+        let mut found: Vec<(&String, &Edge)> = self
+            .edges
+            .iter()
+            .filter(|(_, edge)| unsafe {
+                let (t, h) = (rust_agtail(edge.raw), rust_aghead(edge.raw));
+                (t == tail && h == head) || (!directed && t == head && h == tail)
+            })
+            .collect();
+        found.sort_by_key(|(_, edge)| unsafe { (*edge.raw).base.tag.seq() });
+        Ok(found.into_iter().map(|(key, _)| key.clone()).collect())
+    }
+
+    /// Returns the key `edge` is stored under, for use with [`Graph::set_attr_on_edge`] and friends.
+    ///
+    /// Returns `None` for edges that were created behind the graph's back, e.g. by graphviz itself.
+    pub fn edge_key(&self, edge: &EdgeRef<'_>) -> Option<&str> {
+        self.edges
+            .iter()
+            .find(|(_, stored)| unsafe { rust_agmkout(stored.raw) } == edge.raw)
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// the name of the graph, e.g. `G` for `digraph G {}`
+    pub fn name(&self) -> String {
+        unsafe {
+            let name_ptr = agnameof(self.graph as *mut _);
+            if name_ptr.is_null() {
+                return String::new();
+            }
+            CStr::from_ptr(name_ptr).to_string_lossy().into_owned()
         }
     }
 
-    /// create's an empty graph with the given context
+    /// create's an empty directed graph named `G` with the given context
     pub fn empty(ctx: &'c Context) -> Self {
+        Self::with_kind("G", GraphKind::Directed, ctx).expect("\"G\" is a valid graph name")
+    }
+
+    /// Creates an empty graph of the given `kind`, e.g. `strict graph name {}`.
+    ///
+    /// Edges added to an undirected graph are stored under `tail--head` instead of `tail->head`.
+    ///
+    /// # Errors
+    /// - [`Error::InteriorNul`] if `name` contains a NUL byte.
+    /// - [`Error::NullGraph`] if graphviz could not create the graph.
+    pub fn with_kind<S: AsRef<str>>(name: S, kind: GraphKind, ctx: &'c Context) -> Result<Self, Error> {
+        let c_name = CString::new(name.as_ref())?;
         let graph: *mut Agraph_t = unsafe {
-            agopen(
-                CString::new("G").unwrap().as_ptr() as *mut i8,
-                Agdirected,
-                std::ptr::null_mut(),
-            )
+            let desc = match kind {
+                GraphKind::Directed => Agdirected,
+                GraphKind::StrictDirected => Agstrictdirected,
+                GraphKind::Undirected => Agundirected,
+                GraphKind::StrictUndirected => Agstrictundirected,
+            };
+            agopen(c_name.as_ptr() as *mut c_char, desc, std::ptr::null_mut())
         };
-        Self {
+        if graph.is_null() {
+            return Err(Error::NullGraph);
+        }
+        Ok(Self {
             graph,
             layout: None,
+            configured_layout: None,
+            layout_state: LayoutState::None,
             ctx,
             nodes: HashMap::new(),
             edges: HashMap::new(),
-        }
+        })
+    }
+
+    /// whether the graph is directed and/or strict
+    pub fn kind(&self) -> GraphKind {
+        graph_kind(self.graph)
     }
 
     /// Adds a node by name (creates if not exist).
@@ -129,6 +282,7 @@ impl<'c> Graph<'c> {
         } else {
             let node = Node { raw: raw_node };
             self.nodes.insert(name_str, node);
+            self.mark_layout_stale();
             Some(NodeBuilder {
                 graph: self,
                 node_name: name,
@@ -173,6 +327,7 @@ impl<'c> Graph<'c> {
 
         if let Some(edge) = self.add_edge_internal(&tail, &head, &name) {
             self.edges.insert(name.as_ref().to_string(), edge);
+            self.mark_layout_stale();
             Some(name.as_ref().to_string())
         }else{
             None
@@ -205,36 +360,44 @@ impl<'c> Graph<'c> {
     /// this is extensible because of the use of the Attribute trait
     /// but if an invalid type is passed in the failure will happen in C code, not rust code
     /// please use existing Attribute implementations whenever possible, or submit PR to add new attributes.
-    pub fn set_attr_on_node<A>(&mut self, node_name: &str, attr: A) -> Result<(), String>
+    pub fn set_attr_on_node<A>(&mut self, node_name: &str, attr: A) -> Result<(), Error>
     where
         A: Attribute,
     {
         let node = self
             .nodes
             .get(node_name)
-            .ok_or_else(|| format!("Node '{}' not found", node_name))?;
+            .ok_or_else(|| Error::NodeNotFound(node_name.to_string()))?;
 
         let (name_cstr, value_cstr) = attr.to_cstrings();
-        println!("trying to set attribute {:?} with value {:?}", name_cstr, value_cstr);
-        ensure_node_attr(self.graph, name_cstr.clone(), attr.default());
-        set_attr_on_ptr(node.raw as *mut libc::c_void, &name_cstr, &value_cstr)
+        trace!(node = node_name, name = ?name_cstr, value = ?value_cstr, "setting node attribute");
+        set_attr_on_ptr(node.raw as *mut libc::c_void, &name_cstr, &value_cstr, attr.is_html())?;
+        self.mark_layout_stale();
+        Ok(())
     }
 
     /// Set attribute on an edge by name
     /// # Note
     /// Whenever possible use existing Attribute implementations defined in module style
     /// or submit a PR request to add attributes.
-    pub fn set_attr_on_edge<A>(&mut self, edge_name: &str, attr: A) -> Result<(), String>
+    pub fn set_attr_on_edge<A>(&mut self, edge_name: &str, attr: A) -> Result<(), Error>
     where
         A: Attribute,
     {
         let edge = self
             .edges
             .get(edge_name)
-            .ok_or_else(|| format!("Edge '{}' not found", edge_name))?;
+            .ok_or_else(|| Error::EdgeNotFound(edge_name.to_string()))?;
 
         let (name_cstr, value_cstr) = attr.to_cstrings();
-        set_attr_on_ptr(edge.raw as *mut libc::c_void, &name_cstr, &value_cstr)
+        match attr.attr_name() {
+            "tailport" => check_port(unsafe { rust_agtail(edge.raw) }, &value_cstr)?,
+            "headport" => check_port(unsafe { rust_aghead(edge.raw) }, &value_cstr)?,
+            _ => {}
+        }
+        set_attr_on_ptr(edge.raw as *mut libc::c_void, &name_cstr, &value_cstr, attr.is_html())?;
+        self.mark_layout_stale();
+        Ok(())
     }
 
     /// Set attribute on the graph by name
@@ -242,27 +405,90 @@ impl<'c> Graph<'c> {
     /// # Note
     /// Whenever possible use existing Attribute implementations defined in module style
     /// or submit a PR request to add attributes.
-    pub fn set_attr_on_graph<A>(&mut self, attr: A) -> Result<(), String>
+    pub fn set_attr_on_graph<A>(&mut self, attr: A) -> Result<(), Error>
     where
         A: Attribute,
     {
         let (name_cstr, value_cstr) = attr.to_cstrings();
-        set_attr_on_ptr(self.graph as *mut libc::c_void, &name_cstr, &value_cstr)
+        set_attr_on_ptr(self.graph as *mut libc::c_void, &name_cstr, &value_cstr, attr.is_html())?;
+        self.mark_layout_stale();
+        Ok(())
+    }
+
+    /// Sets the default of a node attribute, like `node [shape=box]` in DOT.
+    ///
+    /// Applies to nodes added later and to existing nodes that don't set `attr` themselves.
+    /// graphviz doesn't record which values were set explicitly, so an existing node is told
+    /// apart by still holding the previous default: one set explicitly to that same value
+    /// (`C [color=blue]` under `node [color=blue]`) is updated as well.
+    ///
+    /// # Errors
+    /// Returns [`Error::AttributeFailed`] if graphviz rejects the declaration.
+    pub fn set_default_node_attr(&mut self, attr: NodeAttr) -> Result<(), Error> {
+        self.set_default_attr(AGNODE, &attr)
+    }
+
+    /// Sets the default of an edge attribute, like `edge [color=red]` in DOT.
+    ///
+    /// Applies to edges added later and to existing edges that don't set `attr` themselves.
+    /// graphviz doesn't record which values were set explicitly, so an existing edge is told
+    /// apart by still holding the previous default: one set explicitly to that same value
+    /// (`A -> B [color=blue]` under `edge [color=blue]`) is updated as well.
+    ///
+    /// # Errors
+    /// Returns [`Error::AttributeFailed`] if graphviz rejects the declaration.
+    pub fn set_default_edge_attr(&mut self, attr: EdgeAttr) -> Result<(), Error> {
+        self.set_default_attr(AGEDGE, &attr)
+    }
+
+    /// Sets the default of a graph attribute, like `graph [rankdir=LR]` in DOT.
+    ///
+    /// The default is the graph's own value and is inherited by clusters that don't override it.
+    ///
+    /// # Errors
+    /// Returns [`Error::AttributeFailed`] if graphviz rejects the declaration.
+    pub fn set_default_graph_attr(&mut self, attr: GraphAttr) -> Result<(), Error> {
+        self.set_default_attr(AGRAPH, &attr)
+    }
+
+    /// Applies `theme`: its node and edge attributes become defaults, so attributes set on
+    /// single nodes and edges still win, and its layout engine is used by the next
+    /// [`relayout`](Self::relayout).
+    ///
+    /// # Errors
+    /// Returns [`Error::AttributeFailed`] if graphviz rejects one of the attributes.
+    pub fn apply_theme(&mut self, theme: &GraphTheme) -> Result<(), Error> {
+        self.set_default_graph_attr(GraphAttr::Layout(theme.layout))?;
+        for attr in &theme.node_attrs {
+            self.set_default_node_attr(attr.clone())?;
+        }
+        for attr in &theme.edge_attrs {
+            self.set_default_edge_attr(attr.clone())?;
+        }
+        self.configured_layout = Some(theme.layout);
+        Ok(())
+    }
+
+    fn set_default_attr<A: Attribute>(&mut self, kind: u32, attr: &A) -> Result<(), Error> {
+        let (name_cstr, value_cstr) = attr.to_cstrings();
+        trace!(graph = %self.name(), kind, name = ?name_cstr, value = ?value_cstr, "setting default attribute");
+        set_default_attr(self.graph, kind, &name_cstr, &value_cstr, attr.is_html())?;
+        self.mark_layout_stale();
+        Ok(())
     }
 
     /// Set attribute on the graph itself
-    pub fn set_graph_attr<A>(&mut self, attr: A) -> Result<(), String>
+    pub fn set_graph_attr<A>(&mut self, attr: A) -> Result<(), Error>
     where
         A: Attribute,
     {
         let (name_cstr, value_cstr) = attr.to_cstrings();
-        println!("created cstrings in set_graph_attr, {:?}, {:?}", name_cstr, value_cstr);
 
         if self.graph.is_null() {
-            return Err("graph is null".into());
+            return Err(Error::NullGraph);
         }
         let def = CString::new("").unwrap();
-        println!("ptr values: {:?}, {:?}, {:?}", self.graph, name_cstr.as_ptr(), value_cstr.as_ptr());
+        trace!(graph = %self.name(), name = ?name_cstr, value = ?value_cstr, "setting graph attribute");
         let ret = unsafe {
             agsafeset(
                 self.graph as *mut libc::c_void,
@@ -271,19 +497,15 @@ impl<'c> Graph<'c> {
                 def.as_ptr(),
             )
         };
-        println!("called agsafeset successfully");
         if ret != 0 {
-            Err(format!(
-                "Failed to set graph attribute '{}'='{}'",
-                name_cstr.to_string_lossy(),
-                value_cstr.to_string_lossy()
-            ))
+            Err(attribute_failed(&name_cstr, &value_cstr))
         } else {
+            self.mark_layout_stale();
             Ok(())
         }
     }
 
-    fn set_attribute_cstrings(&mut self, name: &CString, value: &CString) -> Result<(), String> {
+    fn set_attribute_cstrings(&mut self, name: &CString, value: &CString) -> Result<(), Error> {
         let def = std::ptr::null();
         let ret = unsafe {
             agsafeset(
@@ -294,227 +516,2123 @@ impl<'c> Graph<'c> {
             )
         };
         if ret != 0 {
-            Err(format!(
-                "Failed to set graph attribute '{}'='{}'",
-                name.to_string_lossy(),
-                value.to_string_lossy()
-            ))
+            Err(attribute_failed(name, value))
         } else {
             Ok(())
         }
     }
 
-    /// Sets the layout engine for the graph (e.g., "dot", "neato").
-    ///
-    /// This method must be called before rendering or exporting the graph,
-    /// as the layout engine determines how the graph is positioned and styled.
-    ///
-    /// # Panics
-    /// * If the layout string contains interior null bytes.
-    /// * If the underlying `gvLayout` call fails (non-zero error code).
+    /// Reads the attribute `attr` of the node `node_name` as graphviz stores it.
     ///
-    /// # Arguments
-    /// * `layout` - The chosen layout engine as a [`Layout`] enum.
+    /// While the layout is up to date, `pos`, `width` and `height` return the computed values,
+    /// formatted like `dot -Tdot` writes them.
+    /// Returns `Ok(None)` if the attribute is not set on the node.
     ///
-    /// # Example
-    /// ```none
-    /// graph.set_layout(Layout::Dot);
-    /// ```
-    pub fn set_layout(&mut self, layout: Layout) -> Result<(), String> {
-        self.layout = Some(layout);
-        let layout_str = layout.to_string();
-
-        let c_layout =
-            CString::new(layout_str).expect("Layout string contains interior null bytes");
-
-        // Call the layout function with the layout string pointer
-        let result = unsafe { gvLayout(self.ctx.ctx, self.graph, c_layout.as_ptr()) };
-
-        if result != 0 {
-            return Err(format!("gvLayout failed with error code {}", result));
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn get_attr_on_node(&self, node_name: &str, attr: &str) -> Result<Option<String>, Error> {
+        let node = self
+            .nodes
+            .get(node_name)
+            .ok_or_else(|| Error::NodeNotFound(node_name.to_string()))?;
+        if let Some(value) = self.computed_attr(|| unsafe { node_layout_attr(node.raw, attr) }) {
+            return Ok(Some(value));
         }
-        Ok(())
+        get_attr_on_ptr(node.raw as *mut libc::c_void, attr)
     }
 
-    /// Generates the DOT representation of the current graph.
+    /// Reads the attribute `attr` of the edge `edge_name`, see [`get_attr_on_node`](Self::get_attr_on_node).
     ///
-    /// # Panics
-    /// Panics if no layout engine has been set before calling this method.
-    /// Make sure to call [`set_layout`] first.
+    /// The computed values are `pos` and `lp`.
     ///
-    /// # Returns
-    /// * `Ok(String)` containing the DOT format of the graph.
-    /// * `Err(std::string::FromUtf8Error)` if the generated data is not valid UTF-8.
+    /// # Errors
+    /// Returns [`Error::EdgeNotFound`] if no edge with `edge_name` exists.
+    pub fn get_attr_on_edge(&self, edge_name: &str, attr: &str) -> Result<Option<String>, Error> {
+        let edge = self
+            .edges
+            .get(edge_name)
+            .ok_or_else(|| Error::EdgeNotFound(edge_name.to_string()))?;
+        if let Some(value) = self.computed_attr(|| unsafe { edge_layout_attr(edge.raw, attr) }) {
+            return Ok(Some(value));
+        }
+        get_attr_on_ptr(edge.raw as *mut libc::c_void, attr)
+    }
+
+    /// Reads the graph attribute `attr`, e.g. the bounding box `bb` after a layout.
     ///
-    /// # Example
-    /// ```none
-    /// graph.set_layout(Layout::Dot);
-    /// let dot_output = graph.to_dot().unwrap();
-    /// println!("{}", dot_output);
-    /// ```
-    pub fn to_dot(&self) -> Result<String, String> {
-        if self.layout.is_none() {
-            panic!("called to_dot without setting layout");
+    /// The computed values are `bb` and `lp`, see [`get_attr_on_node`](Self::get_attr_on_node).
+    pub fn get_attr_on_graph(&self, attr: &str) -> Result<Option<String>, Error> {
+        if let Some(value) = self.computed_attr(|| unsafe { graph_layout_attr(self.graph, attr) }) {
+            return Ok(Some(value));
         }
-        let data = self.ctx.render(self, OutputFormat::Dot)?;
-        Ok(String::from_utf8(data).unwrap())
+        get_attr_on_ptr(self.graph as *mut libc::c_void, attr)
+    }
+
+    /// runs `read` on the layout records, only while they hold an up to date layout
+    fn computed_attr(&self, read: impl FnOnce() -> Option<String>) -> Option<String> {
+        (self.layout_state == LayoutState::Current).then(read).flatten()
     }
 
-    /// Creates a new cluster subgraph within this graph.
+    /// Reads the attribute `attr` of the node `node_name` as a [`NodeAttribute`].
     ///
-    /// # Parameters
-    /// - `name`: The name of the cluster (without the `"cluster_"` prefix).
+    /// # Errors
+    /// - [`Error::NodeNotFound`] if no node with `node_name` exists.
+    /// - [`Error::ParseFailed`] if the value is not understood by the typed attributes in [`crate::style`].
+    pub fn get_node_attribute(&self, node_name: &str, attr: &str) -> Result<Option<NodeAttribute>, Error> {
+        self.get_attr_on_node(node_name, attr)?
+            .map(|value| NodeAttribute::try_from((attr, value.as_str())))
+            .transpose()
+    }
+
+    /// Reads the attribute `attr` of the edge `edge_name` as an [`EdgeAttribute`].
     ///
-    /// # Returns
-    /// Returns a new `Graph` instance wrapping the created cluster.
+    /// # Errors
+    /// - [`Error::EdgeNotFound`] if no edge with `edge_name` exists.
+    /// - [`Error::ParseFailed`] if the value is not understood by the typed attributes in [`crate::style`].
+    pub fn get_edge_attribute(&self, edge_name: &str, attr: &str) -> Result<Option<EdgeAttribute>, Error> {
+        self.get_attr_on_edge(edge_name, attr)?
+            .map(|value| EdgeAttribute::try_from((attr, value.as_str())))
+            .transpose()
+    }
+
+    /// Reads the graph attribute `attr` as a [`GraphAttr`].
     ///
     /// # Errors
-    /// Returns `Err` if the cluster could not be created (e.g., due to invalid name or allocation failure).
-    pub fn add_cluster<S: AsRef<str>>(&mut self, name: S) -> Result<Graph<'c>, String> {
-        // Prepend "cluster_" to comply with Graphviz convention
-        let cluster_name = format!("cluster_{}", name.as_ref());
+    /// Returns [`Error::ParseFailed`] if the value is not understood by [`GraphAttr`].
+    pub fn get_graph_attribute(&self, attr: &str) -> Result<Option<GraphAttr>, Error> {
+        self.get_attr_on_graph(attr)?
+            .map(|value| GraphAttr::try_from((attr, value.as_str())))
+            .transpose()
+    }
+
+    /// Iterates over the `(name, value)` pairs of all attributes set on the node `node_name`.
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn node_attrs(&self, node_name: &str) -> Result<Attributes<'_>, Error> {
+        let node = self
+            .nodes
+            .get(node_name)
+            .ok_or_else(|| Error::NodeNotFound(node_name.to_string()))?;
+        Ok(Attributes::new(self, AGNODE, node.raw as *mut libc::c_void))
+    }
 
-        // Convert to CString; handle potential interior null byte error
-        let c_cluster_name = CString::new(cluster_name)
-            .map_err(|_| "Cluster name contains interior null byte".to_string())?;
+    /// Iterates over the `(name, value)` pairs of all attributes set on the edge `edge_name`.
+    ///
+    /// # Errors
+    /// Returns [`Error::EdgeNotFound`] if no edge with `edge_name` exists.
+    pub fn edge_attrs(&self, edge_name: &str) -> Result<Attributes<'_>, Error> {
+        let edge = self
+            .edges
+            .get(edge_name)
+            .ok_or_else(|| Error::EdgeNotFound(edge_name.to_string()))?;
+        Ok(Attributes::new(self, AGEDGE, edge.raw as *mut libc::c_void))
+    }
 
-        // Call agsubg to create the cluster
-        let cluster_ptr = unsafe { agsubg(self.graph, c_cluster_name.as_ptr() as *mut c_char, 1) };
+    /// Iterates over the `(name, value)` pairs of all attributes set on the graph itself.
+    pub fn graph_attrs(&self) -> Attributes<'_> {
+        Attributes::new(self, AGRAPH, self.graph as *mut libc::c_void)
+    }
 
-        if cluster_ptr.is_null() {
-            return Err("Failed to create cluster".into());
+    /// Iterates over all nodes in graphviz's order, which is the order they were created in.
+    pub fn nodes(&self) -> Nodes<'_> {
+        Nodes {
+            graph: self.graph,
+            next: unsafe { agfstnode(self.graph) },
+            _graph: PhantomData,
         }
+    }
 
-        // Wrap in new Graph struct
-        Ok(Graph {
-            graph: cluster_ptr,
-            layout: None,                    // cluster starts without layout
-            ctx: self.ctx,                   // reuse the same context
-            nodes: HashMap::new(),           // cluster initially empty
-            edges: HashMap::new(),
-        })
+    /// Iterates over all edges, grouped by their tail node.
+    pub fn edges(&self) -> Edges<'_> {
+        let node = unsafe { agfstnode(self.graph) };
+        let next = if node.is_null() { std::ptr::null_mut() } else { unsafe { agfstout(self.graph, node) } };
+        Edges::new(self.graph, EdgeWalk::Graph, node, next)
     }
-}
 
-fn ensure_node_attr(graph: *mut Agraph_t, name: CString, default: &str) {
-    
-    let name_cstr = CString::new(name).unwrap();
-    let default_cstr = CString::new(default).unwrap();
-    unsafe {
-        agattr(
-            graph,
-            AGNODE.try_into().unwrap(),
-            name_cstr.as_ptr() as *mut i8,
-            default_cstr.as_ptr() as *mut i8
-        );
+    /// Iterates over the edges leaving `node_name`.
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn out_edges(&self, node_name: &str) -> Result<Edges<'_>, Error> {
+        let node = self.node_ptr(node_name)?;
+        let next = unsafe { agfstout(self.graph, node) };
+        Ok(Edges::new(self.graph, EdgeWalk::Out, node, next))
     }
-}
 
-fn set_attr_on_ptr(obj: *mut libc::c_void, name: &CString, value: &CString) -> Result<(), String> {
-    let def = CString::new("").unwrap();
-    let ret = unsafe {
-        agsafeset_text(
-            obj,
-            name.as_ptr() as *mut i8,
-            value.as_ptr(),
-            def.as_ptr(),
-        )
-    };
-    if ret != 0 {
-        Err(format!(
-            "Failed to set attribute '{}'='{}'",
-            name.to_string_lossy(),
-            value.to_string_lossy()
-        ))
-    } else {
-        Ok(())
+    /// Iterates over the edges entering `node_name`.
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn in_edges(&self, node_name: &str) -> Result<Edges<'_>, Error> {
+        let node = self.node_ptr(node_name)?;
+        let next = unsafe { agfstin(self.graph, node) };
+        Ok(Edges::new(self.graph, EdgeWalk::In, node, next))
     }
-}
 
-impl<'c> std::ops::Drop for Graph<'c> {
-    fn drop(&mut self) {
-        unsafe {
-            if let Some(_) = self.layout {
-                //gvFreeLayout(self.ctx.ctx, self.graph);
-            }
-            //println!("freed layout");
-            agclose(self.graph);
-        }
+    /// Iterates over all edges incident to `node_name`, in and out, which is what
+    /// "the edges of a node" means in an undirected graph. Self loops are returned once.
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn incident_edges(&self, node_name: &str) -> Result<Edges<'_>, Error> {
+        let node = self.node_ptr(node_name)?;
+        let next = unsafe { agfstedge(self.graph, node) };
+        Ok(Edges::new(self.graph, EdgeWalk::All, node, next))
     }
-}
 
-/// Created from `sys::gvContext()` this struct is used to initialize graphs
-pub struct Context {
-    ctx: *mut GVC_t,
-}
+    /// Iterates over the heads of the edges leaving `node_name`, once per edge.
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn successors(&self, node_name: &str) -> Result<impl Iterator<Item = NodeRef<'_>>, Error> {
+        Ok(self.out_edges(node_name)?.map(|edge| edge.head))
+    }
 
-impl Context {
-    pub fn new() -> Context {
-        Self {
-            ctx: unsafe { gvContext() },
-        }
+    /// Iterates over the tails of the edges entering `node_name`, once per edge.
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn predecessors(&self, node_name: &str) -> Result<impl Iterator<Item = NodeRef<'_>>, Error> {
+        Ok(self.in_edges(node_name)?.map(|edge| edge.tail))
     }
 
-    /// Renders the provided `graph` into the specified `format` using Graphviz.
+    /// Iterates over the nodes at the other end of every edge incident to `node_name`,
+    /// ignoring the edges' direction.
     ///
-    /// # Arguments
-    /// - `graph`: The graph to render.
-    /// - `format`: The desired output format (e.g., `OutputFormat::Svg`, `OutputFormat::Png`).
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn neighbors(&self, node_name: &str) -> Result<impl Iterator<Item = NodeRef<'_>>, Error> {
+        let node = self.node_ptr(node_name)?;
+        Ok(self
+            .incident_edges(node_name)?
+            .map(move |edge| if edge.tail.raw == node { edge.head } else { edge.tail }))
+    }
+
+    /// the number of edges entering and leaving `node_name`, a self loop counts twice
     ///
-    /// # Returns
-    /// A `Vec<u8>` containing the rendered graph output (e.g., SVG or image bytes).
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn degree(&self, node_name: &str) -> Result<usize, Error> {
+        self.degree_of(node_name, true, true)
+    }
+
+    /// the number of edges entering `node_name`
     ///
-    /// # Panics
-    /// Panics if rendering fails or if the format string cannot be converted to a C string.
-    pub fn render(&self, graph: &Graph, format: OutputFormat) -> Result<Vec<u8>, String> {
-        let format_cstr = CString::new(format.to_string())
-            .expect("Failed to convert output format to CString");
-        let result_str = CString::new("").unwrap();
-        let mut result_ptr: *mut std::os::raw::c_char = result_str.as_ptr() as *mut std::os::raw::c_char;
-        let mut length: usize = 0;
-    
-        unsafe {
-            println!("about to render data with ptrs: {:?}, {:?}, {:?}, {:?}, {:?}", self.ctx, graph.graph, format_cstr.as_ptr(), &mut result_ptr, &mut length as *mut usize);
-            if gvRenderData(self.ctx, graph.graph, format_cstr.as_ptr(), &mut result_ptr, &mut length as *mut usize) != 0 {
-                return Err("Graphviz render failed".into());
-            }
-            println!("after gvRender");
-            let output = std::slice::from_raw_parts(result_ptr as *const u8, length).to_vec();
-            println!("tried to construct output");
-            gvFreeRenderData(result_ptr);
-            Ok(output)
-        }
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn in_degree(&self, node_name: &str) -> Result<usize, Error> {
+        self.degree_of(node_name, true, false)
     }
-}
 
-/// wrapper struct for `sys::Agnode_t` for handling graph nodes
-#[derive(Clone)]
-pub struct Node {
-    raw: *mut Agnode_t,
-}
+    /// the number of edges leaving `node_name`
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_name` exists.
+    pub fn out_degree(&self, node_name: &str) -> Result<usize, Error> {
+        self.degree_of(node_name, false, true)
+    }
 
+    fn degree_of(&self, node_name: &str, incoming: bool, outgoing: bool) -> Result<usize, Error> {
+        let node = self.node_ptr(node_name)?;
+        let degree = unsafe { agdegree(self.graph, node, incoming as c_int, outgoing as c_int) };
+        Ok(degree.max(0) as usize)
+    }
 
+    fn node_ptr(&self, node_name: &str) -> Result<*mut Agnode_t, Error> {
+        self.nodes
+            .get(node_name)
+            .map(|node| node.raw)
+            .ok_or_else(|| Error::NodeNotFound(node_name.to_string()))
+    }
 
-/// wrapper struct for `sys::Agedge_t` for adding edges between nodes
-pub struct Edge {
-    raw: *mut Agedge_t,
-}
+    /// Lays the graph out with `engine` (e.g. "dot", "neato"), replacing any earlier layout.
+    ///
+    /// This method must be called before rendering or exporting the graph, and again after the
+    /// graph was edited, see [`is_layout_stale`](Self::is_layout_stale).
+    /// The computed positions and sizes can be read back as the attributes `pos`, `width`, `height`,
+    /// `bb` and `lp`, see [`get_attr_on_node`](Self::get_attr_on_node), the graph's own attributes
+    /// are left untouched so a later layout starts from the same input.
+    ///
+    /// # Errors
+    /// Returns [`Error::LayoutFailed`] with the error code if the underlying `gvLayout` call fails,
+    /// the graph is left without a layout in that case.
+    ///
+    /// # Example
+    /// ```none
+    /// graph.layout(Layout::Dot)?;
+    /// ```
+    pub fn layout(&mut self, engine: Layout) -> Result<(), Error> {
+        let c_layout = CString::new(engine.to_string())?;
+        // gvLayout doesn't release an earlier layout itself
+        self.clear_layout();
+        self.layout = Some(engine);
+        self.configured_layout = Some(engine);
 
-impl std::ops::Drop for Context {
-    fn drop(&mut self) {
-        unsafe {
-            gvFreeContext(self.ctx);
+        // Call the layout function with the layout string pointer
+        let (result, diagnostics) = self
+            .ctx
+            .capture(|| unsafe { gvLayout(self.ctx.ctx, self.graph, c_layout.as_ptr()) });
+
+        if result != 0 {
+            // a failed layout can leave partial records behind
+            unsafe { gvFreeLayout(self.ctx.ctx, self.graph) };
+            return Err(Error::LayoutFailed { code: result, diagnostics });
         }
+        self.layout_state = LayoutState::Current;
+        debug!(graph = %self.name(), layout = %engine, "laid out graph");
+        Ok(())
     }
-}
 
-pub trait DotGraph {
-    type Err: std::error::Error;
-    fn generate_dot(&self) -> Result<String, Self::Err>;
-}
+    /// Same as [`layout`](Self::layout).
+    pub fn set_layout(&mut self, layout: Layout) -> Result<(), Error> {
+        self.layout(layout)
+    }
 
-pub trait GraphEngine {
-    type Err: std::error::Error;
+    /// Lays the graph out again with the engine of the last layout or theme, e.g. after editing it.
+    ///
+    /// # Errors
+    /// Returns [`Error::NotLaidOut`] if no layout engine ran on this graph and no theme set one.
+    pub fn relayout(&mut self) -> Result<(), Error> {
+        let engine = self.configured_layout.ok_or(Error::NotLaidOut)?;
+        self.layout(engine)
+    }
+
+    /// Frees the current layout, if any. The graph has to be laid out again before rendering.
+    pub fn clear_layout(&mut self) {
+        if self.layout_state != LayoutState::None {
+            unsafe { gvFreeLayout(self.ctx.ctx, self.graph) };
+            self.layout_state = LayoutState::None;
+        }
+    }
+
+    /// the engine used for the last layout, kept after [`clear_layout`](Self::clear_layout)
+    pub fn layout_engine(&self) -> Option<Layout> {
+        self.layout
+    }
+
+    /// the engine [`relayout`](Self::relayout) uses: the one of the last layout or,
+    /// if a theme was applied since, the theme's
+    pub fn configured_layout(&self) -> Option<Layout> {
+        self.configured_layout
+    }
+
+    /// returns `true` if the graph has a layout, even a stale one
+    pub fn is_laid_out(&self) -> bool {
+        self.layout_state != LayoutState::None
+    }
+
+    /// returns `true` if the graph was edited after its last layout
+    pub fn is_layout_stale(&self) -> bool {
+        self.layout_state == LayoutState::Stale
+    }
+
+    fn mark_layout_stale(&mut self) {
+        if self.layout_state == LayoutState::Current {
+            self.layout_state = LayoutState::Stale;
+        }
+    }
+
+    /// fails unless the graph has an up to date layout
+    fn check_layout(&self) -> Result<(), Error> {
+        match self.layout_state {
+            LayoutState::Current => Ok(()),
+            LayoutState::Stale => Err(Error::StaleLayout),
+            LayoutState::None => Err(Error::NotLaidOut),
+        }
+    }
+
+    /// Reads the positions, sizes and edge routes computed by the last layout.
+    ///
+    /// # Errors
+    /// - [`Error::NotLaidOut`] if [`layout`](Self::layout) has not been called.
+    /// - [`Error::StaleLayout`] if the graph was edited after the last layout.
+    pub fn layout_info(&self) -> Result<GraphLayout, Error> {
+        self.check_layout()?;
+        let info = unsafe { agdata::<Agraphinfo_t>(self.graph as *mut _) };
+        if info.is_null() {
+            return Err(Error::NotLaidOut);
+        }
+
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        for (name, node) in &self.nodes {
+            let info = unsafe { agdata::<Agnodeinfo_t>(node.raw as *mut _) };
+            if info.is_null() {
+                continue;
+            }
+            let info = unsafe { &*info };
+            nodes.push(NodeLayout {
+                name: name.clone(),
+                position: info.coord.into(),
+                // ND_width and ND_height are in inches
+                width: info.width * POINTS_PER_INCH,
+                height: info.height * POINTS_PER_INCH,
+                label: unsafe { label_layout(info.label, Some(info.coord.into())) },
+                xlabel: unsafe { label_layout(info.xlabel, None) },
+            });
+        }
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut edges = Vec::with_capacity(self.edges.len());
+        for (name, edge) in &self.edges {
+            let info = unsafe { agdata::<Agedgeinfo_t>(edge.raw as *mut _) };
+            if info.is_null() {
+                continue;
+            }
+            let info = unsafe { &*info };
+            edges.push(EdgeLayout {
+                name: name.clone(),
+                tail: unsafe { object_name(rust_agtail(edge.raw) as *mut _) },
+                head: unsafe { object_name(rust_aghead(edge.raw) as *mut _) },
+                splines: unsafe { spline_layout(info.spl) },
+                label: unsafe { label_layout(info.label, None) },
+                head_label: unsafe { label_layout(info.head_label, None) },
+                tail_label: unsafe { label_layout(info.tail_label, None) },
+                xlabel: unsafe { label_layout(info.xlabel, None) },
+            });
+        }
+        edges.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let info = unsafe { &*info };
+        Ok(GraphLayout {
+            bounding_box: info.bb.into(),
+            label: unsafe { label_layout(info.label, None) },
+            nodes,
+            edges,
+        })
+    }
+
+    /// Generates the DOT representation of the current graph.
+    ///
+    /// # Returns
+    /// * `Ok(String)` containing the DOT format of the graph.
+    /// * `Err(Error::NotLaidOut)` or `Err(Error::StaleLayout)` without an up to date layout,
+    ///   call [`layout`](Self::layout) first.
+    /// * `Err(Error::InvalidUtf8)` if the generated data is not valid UTF-8.
+    ///
+    /// # Example
+    /// ```none
+    /// graph.set_layout(Layout::Dot);
+    /// let dot_output = graph.to_dot().unwrap();
+    /// println!("{}", dot_output);
+    /// ```
+    pub fn to_dot(&self) -> Result<String, Error> {
+        let data = self.ctx.render(self, OutputFormat::Dot)?;
+        Ok(String::from_utf8(data)?)
+    }
+
+    /// An independent copy of the graph's structure and attributes on `ctx`, written with
+    /// `agwrite` and read back, without the layout.
+    fn duplicate<'d>(&self, ctx: &'d Context) -> Result<Graph<'d>, Error> {
+        let (raw, diagnostics) = ctx.capture(|| unsafe {
+            let file = libc::tmpfile();
+            if file.is_null() {
+                return Err(Error::Io(std::io::Error::last_os_error()));
+            }
+            let written = agwrite(self.graph, file as *mut libc::c_void);
+            libc::rewind(file);
+            let raw = if written == 0 { agread(file as *mut libc::c_void, std::ptr::null_mut()) } else { std::ptr::null_mut() };
+            libc::fclose(file);
+            Ok(raw)
+        });
+        let raw = raw?;
+        if raw.is_null() {
+            return Err(Error::InvalidDot(diagnostics));
+        }
+        let mut copy = Graph::from_raw(raw, ctx);
+        copy.configured_layout = self.configured_layout;
+        Ok(copy)
+    }
+
+    /// Removes the node `name` together with all edges incident to it.
+    ///
+    /// The node also disappears from every cluster it was part of.
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `name` exists.
+    pub fn remove_node(&mut self, name: &str) -> Result<(), Error> {
+        let node = self
+            .nodes
+            .remove(name)
+            .ok_or_else(|| Error::NodeNotFound(name.to_string()))?;
+        self.invalidate_layout();
+        self.edges.retain(|_, edge| unsafe {
+            rust_agtail(edge.raw) != node.raw && rust_aghead(edge.raw) != node.raw
+        });
+        unsafe { agdelnode(self.graph, node.raw) };
+        Ok(())
+    }
+
+    /// Removes the edge stored under `name`, leaving its nodes in place.
+    ///
+    /// # Errors
+    /// Returns [`Error::EdgeNotFound`] if no edge with `name` exists.
+    pub fn remove_edge(&mut self, name: &str) -> Result<(), Error> {
+        let edge = self
+            .edges
+            .remove(name)
+            .ok_or_else(|| Error::EdgeNotFound(name.to_string()))?;
+        self.invalidate_layout();
+        unsafe { agdeledge(self.graph, edge.raw) };
+        Ok(())
+    }
+
+    /// Removes the cluster `name` (without the `"cluster_"` prefix) and the clusters nested in it.
+    /// Nested clusters can be removed by their name too.
+    ///
+    /// Nodes and edges of the cluster stay in the graph, only the grouping is removed and freed.
+    ///
+    /// # Errors
+    /// - [`Error::ClusterNotFound`] if this graph has no such cluster.
+    /// - [`Error::ClusterRemoveFailed`] if graphviz could not delete it.
+    pub fn remove_cluster<S: AsRef<str>>(&mut self, name: S) -> Result<(), Error> {
+        let cluster_name = format!("cluster_{}", name.as_ref());
+        let raw = find_subgraph(self.graph, &cluster_name)?;
+        self.invalidate_layout();
+        // nested clusters can only be deleted through the subgraph that contains them
+        let ret = unsafe { agdelete(agparent(raw), raw as *mut libc::c_void) };
+        if ret != 0 {
+            return Err(Error::ClusterRemoveFailed(cluster_name));
+        }
+        Ok(())
+    }
+
+    /// Frees the layout before the graph's structure changes and marks it stale.
+    ///
+    /// Graphviz walks the graph's nodes and edges to release their layout records, so they must
+    /// be released while everything is still in place. Freeing twice is a no-op in graphviz,
+    /// so the later `gvFreeLayout` in `layout` or `Drop` is harmless.
+    fn invalidate_layout(&mut self) {
+        if self.layout_state != LayoutState::None {
+            unsafe { gvFreeLayout(self.ctx.ctx, self.graph) };
+            self.layout_state = LayoutState::Stale;
+        }
+    }
+
+    /// Creates a new cluster subgraph within this graph, or returns the existing one.
+    ///
+    /// # Parameters
+    /// - `name`: The name of the cluster (without the `"cluster_"` prefix).
+    ///
+    /// # Returns
+    /// A [`Subgraph`] handle borrowing this graph, the cluster itself is owned and freed by the root graph.
+    ///
+    /// # Errors
+    /// Returns [`Error::ClusterFailed`] if graphviz could not create the cluster.
+    pub fn add_cluster<S: AsRef<str>>(&mut self, name: S) -> Result<Subgraph<'_, 'c>, Error> {
+        // Prepend "cluster_" to comply with Graphviz convention
+        let cluster_name = format!("cluster_{}", name.as_ref());
+        let raw = create_subgraph(self.graph, &cluster_name)?;
+        self.mark_layout_stale();
+        Ok(Subgraph { raw, root: self })
+    }
+
+    /// Returns a handle to the cluster `name` (without the `"cluster_"` prefix), searching nested clusters too.
+    ///
+    /// # Errors
+    /// Returns [`Error::ClusterNotFound`] if there is no such cluster.
+    pub fn cluster<S: AsRef<str>>(&mut self, name: S) -> Result<Subgraph<'_, 'c>, Error> {
+        self.subgraph(format!("cluster_{}", name.as_ref()))
+    }
+
+    /// Returns a handle to the subgraph with the full name `name`, e.g. one read from DOT,
+    /// searching nested subgraphs too.
+    ///
+    /// # Errors
+    /// Returns [`Error::ClusterNotFound`] if there is no such subgraph.
+    pub fn subgraph<S: AsRef<str>>(&mut self, name: S) -> Result<Subgraph<'_, 'c>, Error> {
+        let raw = find_subgraph(self.graph, name.as_ref())?;
+        Ok(Subgraph { raw, root: self })
+    }
+
+    /// Iterates over the subgraphs directly below the root graph, clusters or not.
+    pub fn subgraphs(&self) -> Subgraphs<'_> {
+        Subgraphs::new(self.graph)
+    }
+
+    /// Returns all clusters of the graph, nested ones included, parents before their children.
+    pub fn clusters(&self) -> Vec<SubgraphRef<'_>> {
+        fn collect<'g>(subgraphs: Subgraphs<'g>, clusters: &mut Vec<SubgraphRef<'g>>) {
+            for subgraph in subgraphs {
+                let children = subgraph.subgraphs();
+                if subgraph.is_cluster() {
+                    clusters.push(subgraph);
+                }
+                collect(children, clusters);
+            }
+        }
+        let mut clusters = Vec::new();
+        collect(self.subgraphs(), &mut clusters);
+        clusters
+    }
+
+    /// Builds a graph from `graph` through the C API: its attributes and defaults, nodes,
+    /// edges and clusters, nested ones included.
+    ///
+    /// Edges are stored under their [`RustGraph`] ids. Nodes and edges that only a cluster
+    /// lists are created in the root graph too, like graphviz does for DOT.
+    ///
+    /// # Errors
+    /// - [`Error::NodeNotFound`] if an edge refers to a node that doesn't exist.
+    /// - [`Error::EdgeNotFound`] if graphviz could not create an edge.
+    /// - [`Error::AttributeFailed`] or [`Error::PortNotFound`] if an attribute is rejected.
+    pub fn from_rust_graph(graph: &RustGraph, ctx: &'c Context) -> Result<Self, Error> {
+        let mut cgraph = Self::with_kind(graph.name(), graph.kind(), ctx)?;
+        // defaults go first so the nodes and edges created below pick them up
+        copy_settings(cgraph.graph, graph)?;
+
+        let mut nodes = BTreeMap::new();
+        let mut edges = BTreeMap::new();
+        collect_members(graph, &mut nodes, &mut edges);
+        for (id, node) in &nodes {
+            cgraph
+                .add_node(*id)
+                .ok_or_else(|| Error::NodeNotFound(id.to_string()))?;
+            if node.label() != *id {
+                cgraph.set_attr_on_node(id, CommonAttr::Label(node.label().to_string()))?;
+            }
+            for attr in node.attributes() {
+                cgraph.set_attr_on_node(id, attr.clone())?;
+            }
+        }
+        for (id, edge) in &edges {
+            cgraph.node_ptr(edge.source())?;
+            cgraph.node_ptr(edge.dest())?;
+            cgraph
+                .add_edge(edge.source(), edge.dest(), *id)
+                .ok_or_else(|| Error::EdgeNotFound(id.to_string()))?;
+            for attr in edge.attributes() {
+                cgraph.set_attr_on_edge(id, attr.clone())?;
+            }
+        }
+
+        let root = cgraph.graph;
+        cgraph.copy_clusters(root, graph)?;
+        Ok(cgraph)
+    }
+
+    fn copy_clusters(&mut self, parent: *mut Agraph_t, graph: &RustGraph) -> Result<(), Error> {
+        let mut clusters: Vec<_> = graph.clusters().collect();
+        clusters.sort_by_key(|(name, _)| *name);
+        for (name, cluster) in clusters {
+            let raw = create_subgraph(parent, name)?;
+            let mut subgraph = Subgraph { raw, root: self };
+            for node in cluster.nodes() {
+                subgraph.add_node(node.id())?;
+            }
+            for edge in cluster.edges() {
+                subgraph.include_edge(edge.id())?;
+            }
+            // like in DOT, the cluster's node and edge defaults only apply to objects created in it
+            // later, members that already exist in the root graph keep their own attributes
+            copy_settings(raw, cluster)?;
+            self.copy_clusters(raw, cluster)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the graph back into a [`RustGraph`], e.g. after parsing DOT or a layout.
+    ///
+    /// Attributes, node and edge defaults, nodes, edges and clusters are converted with the
+    /// typed attributes in [`crate::style`], values they don't understand, like the `pos`
+    /// written by [`to_dot`](Self::to_dot), are left out. Edges keep the keys they are stored under. Subgraphs
+    /// that aren't clusters are dropped, the clusters inside them move up to the enclosing graph.
+    pub fn to_rust_graph(&self) -> RustGraph {
+        let mut graph = RustGraph::with_kind(self.name(), self.kind());
+        read_settings(self.graph, self.graph, &mut graph);
+
+        for node in self.nodes() {
+            let raw = node.raw as *mut libc::c_void;
+            let mut label = node.name().to_string();
+            let mut attributes = Vec::new();
+            for (name, value, html) in unsafe { object_attrs(self.graph, AGNODE, raw) } {
+                if name == "label" && !html {
+                    label = value;
+                } else if let Some(attr) = typed_attr::<NodeAttribute>(&name, value, html) {
+                    attributes.push(attr);
+                }
+            }
+            let mut converted = rgraph::Node::new(node.name(), label.as_str());
+            for attr in attributes {
+                converted.set_attr(attr);
+            }
+            graph.add_node(converted);
+        }
+
+        // walk graphviz's edges so the order is stable and edges missing from `self.edges` are kept,
+        // those get a key the way `parse` would have stored them
+        let stored: HashMap<*mut Agedge_t, &str> = self
+            .edges
+            .iter()
+            .map(|(key, edge)| (unsafe { rust_agmkout(edge.raw) }, key.as_str()))
+            .collect();
+        let mut taken: HashMap<String, Edge> =
+            self.edges.iter().map(|(key, edge)| (key.clone(), Edge { raw: edge.raw })).collect();
+        let edge_op = self.kind().edge_op();
+        for edge in self.edges() {
+            let key = match stored.get(&edge.raw) {
+                Some(key) => key.to_string(),
+                None => {
+                    let key = unsafe { edge_key(edge.raw, edge_op, &taken) };
+                    taken.insert(key.clone(), Edge { raw: edge.raw });
+                    key
+                }
+            };
+            let edge = Edge { raw: edge.raw };
+            let (tail, head) = unsafe {
+                (object_name(rust_agtail(edge.raw) as *mut _), object_name(rust_aghead(edge.raw) as *mut _))
+            };
+            let mut converted = rgraph::Edge::new(key.as_str(), tail.as_str(), head.as_str());
+            for (name, value, html) in unsafe { object_attrs(self.graph, AGEDGE, edge.raw as *mut _) } {
+                // the key is the edge's name, not an attribute
+                if name == "key" {
+                    continue;
+                }
+                if let Some(attr) = typed_attr::<EdgeAttribute>(&name, value, html) {
+                    converted.set_attr(attr);
+                }
+            }
+            graph.add_edge(converted);
+        }
+
+        for cluster in self.read_clusters(self.subgraphs(), &graph) {
+            graph.add_cluster(cluster).expect("adding a cluster to a RustGraph can't fail");
+        }
+        graph
+    }
+
+    /// converts the clusters among `subgraphs`, taking their nodes and edges from `root`
+    fn read_clusters(&self, subgraphs: Subgraphs<'_>, root: &RustGraph) -> Vec<RustGraph> {
+        let mut clusters = Vec::new();
+        for subgraph in subgraphs {
+            if !subgraph.is_cluster() {
+                clusters.extend(self.read_clusters(subgraph.subgraphs(), root));
+                continue;
+            }
+            let name = subgraph.name();
+            let name = name.strip_prefix("cluster_").unwrap_or(&name);
+            let mut cluster = RustGraph::with_kind(name.to_string(), self.kind());
+            read_settings(subgraph.raw, unsafe { agparent(subgraph.raw) }, &mut cluster);
+            for node in subgraph.nodes() {
+                if let Some(node) = root.node(node.name()) {
+                    cluster.add_node(node.clone());
+                }
+            }
+            for edge in subgraph.edges() {
+                if let Some(edge) = self.edge_key(&edge).and_then(|key| root.edge(key)) {
+                    cluster.add_edge(edge.clone());
+                }
+            }
+            for nested in self.read_clusters(subgraph.subgraphs(), root) {
+                cluster.add_cluster(nested).expect("adding a cluster to a RustGraph can't fail");
+            }
+            clusters.push(cluster);
+        }
+        clusters
+    }
+}
+
+fn graph_kind(graph: *mut Agraph_t) -> GraphKind {
+    let (directed, strict) = unsafe { (agisdirected(graph) != 0, agisstrict(graph) != 0) };
+    match (directed, strict) {
+        (true, false) => GraphKind::Directed,
+        (true, true) => GraphKind::StrictDirected,
+        (false, false) => GraphKind::Undirected,
+        (false, true) => GraphKind::StrictUndirected,
+    }
+}
+
+/// nodes and edges of `graph` and all its clusters, the root graph's copy wins
+fn collect_members<'r>(
+    graph: &'r RustGraph,
+    nodes: &mut BTreeMap<&'r str, &'r rgraph::Node>,
+    edges: &mut BTreeMap<&'r str, &'r rgraph::Edge>,
+) {
+    for node in graph.nodes() {
+        nodes.entry(node.id()).or_insert(node);
+    }
+    for edge in graph.edges() {
+        edges.entry(edge.id()).or_insert(edge);
+    }
+    for (_, cluster) in graph.clusters() {
+        collect_members(cluster, nodes, edges);
+    }
+}
+
+/// sets the graph attributes, cluster attributes and node and edge defaults of `graph` on `raw`
+fn copy_settings(raw: *mut Agraph_t, graph: &RustGraph) -> Result<(), Error> {
+    let obj = raw as *mut libc::c_void;
+    for attr in graph.attributes() {
+        let (name, value) = attr.to_cstrings();
+        set_attr_on_ptr(obj, &name, &value, attr.is_html())?;
+    }
+    for attr in graph.cluster_attributes() {
+        let (name, value) = attr.to_cstrings();
+        set_attr_on_ptr(obj, &name, &value, attr.is_html())?;
+    }
+    for attr in graph.node_defaults() {
+        let (name, value) = attr.to_cstrings();
+        set_default_attr(raw, AGNODE, &name, &value, attr.is_html())?;
+    }
+    for attr in graph.edge_defaults() {
+        let (name, value) = attr.to_cstrings();
+        set_default_attr(raw, AGEDGE, &name, &value, attr.is_html())?;
+    }
+    Ok(())
+}
+
+/// Reads the attributes and node and edge defaults `raw` sets on top of `parent` into `graph`.
+/// For the root graph `parent` is the graph itself, then everything that is set is read.
+fn read_settings(raw: *mut Agraph_t, parent: *mut Agraph_t, graph: &mut RustGraph) {
+    let root = unsafe { agroot(raw as *mut _) };
+    let mut sym = unsafe { agnxtattr(root, AGRAPH as c_int, std::ptr::null_mut()) };
+    while !sym.is_null() {
+        let (value, inherited) = unsafe { (agxget(raw as *mut _, sym), agxget(parent as *mut _, sym)) };
+        let name = unsafe { CStr::from_ptr((*sym).name) }.to_string_lossy().into_owned();
+        let html = unsafe { aghtmlstr(value) } != 0;
+        let value = unsafe { attr_value(value) };
+        sym = unsafe { agnxtattr(root, AGRAPH as c_int, sym) };
+
+        let Some(value) = value else { continue };
+        if parent != raw && unsafe { CStr::from_ptr(inherited) }.to_string_lossy() == value {
+            continue;
+        }
+        if let (false, Ok(attr)) = (html, GraphAttr::try_from((name.as_str(), value.as_str()))) {
+            graph.add_graph_attr(attr);
+        } else if let Some(attr) = typed_attr::<ClusterAttribute>(&name, value, html) {
+            graph.add_cluster_attr(attr);
+        }
+    }
+
+    for kind in [AGNODE, AGEDGE] {
+        let mut sym = unsafe { agnxtattr(root, kind as c_int, std::ptr::null_mut()) };
+        while !sym.is_null() {
+            let name = unsafe { CStr::from_ptr((*sym).name) }.to_owned();
+            sym = unsafe { agnxtattr(root, kind as c_int, sym) };
+
+            let default = |graph: *mut Agraph_t| unsafe {
+                let sym = agattr_text(graph, kind as c_int, name.as_ptr() as *mut c_char, std::ptr::null());
+                (!sym.is_null()).then(|| CStr::from_ptr((*sym).defval).to_string_lossy().into_owned())
+            };
+            let Some(value) = default(raw).filter(|value| !value.is_empty()) else {
+                continue;
+            };
+            let inherited = if parent == raw { None } else { default(parent) };
+            // `\N`, the node's name, is graphviz's own label default
+            if inherited.as_ref() == Some(&value) || (inherited.is_none() && value == "\\N") {
+                continue;
+            }
+            let pair = (name.to_str().unwrap_or_default(), value.as_str());
+            if kind == AGNODE {
+                if let Ok(attr) = NodeAttr::try_from(pair) {
+                    graph.set_default_node_attr(attr);
+                }
+            } else if let Ok(attr) = EdgeAttr::try_from(pair) {
+                graph.set_default_edge_attr(attr);
+            }
+        }
+    }
+}
+
+/// The `(name, value, html)` triples of the attributes of a node or edge that differ from the
+/// declared default.
+unsafe fn object_attrs(graph: *mut Agraph_t, kind: u32, obj: *mut libc::c_void) -> Vec<(String, String, bool)> {
+    let mut attrs = Vec::new();
+    let mut sym = unsafe { agnxtattr(graph, kind as c_int, std::ptr::null_mut()) };
+    while !sym.is_null() {
+        unsafe {
+            let value = agxget(obj, sym);
+            let differs = !value.is_null() && CStr::from_ptr(value) != CStr::from_ptr((*sym).defval);
+            if let Some(text) = attr_value(value).filter(|_| differs) {
+                let name = CStr::from_ptr((*sym).name).to_string_lossy().into_owned();
+                attrs.push((name, text, aghtmlstr(value) != 0));
+            }
+            sym = agnxtattr(graph, kind as c_int, sym);
+        }
+    }
+    attrs
+}
+
+/// converts an attribute read from graphviz, HTML-like labels become [`CommonAttr::HtmlLabel`]
+fn typed_attr<T>(name: &str, value: String, html: bool) -> Option<T>
+where
+    T: for<'a> TryFrom<(&'a str, &'a str)> + From<CommonAttr>,
+{
+    if html && name == "label" {
+        return Some(CommonAttr::HtmlLabel(HtmlLabel::raw(value)).into());
+    }
+    T::try_from((name, value.as_str())).ok()
+}
+
+/// where a graph is in its layout lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayoutState {
+    None,
+    Current,
+    /// laid out, but edited since
+    Stale,
+}
+
+fn create_subgraph(parent: *mut Agraph_t, name: &str) -> Result<*mut Agraph_t, Error> {
+    let c_name = CString::new(name)?;
+    let raw = unsafe { agsubg(parent, c_name.as_ptr() as *mut c_char, 1) };
+    if raw.is_null() {
+        return Err(Error::ClusterFailed(name.to_string()));
+    }
+    Ok(raw)
+}
+
+/// finds the subgraph `name` anywhere below `parent`
+fn find_subgraph(parent: *mut Agraph_t, name: &str) -> Result<*mut Agraph_t, Error> {
+    fn find(parent: *mut Agraph_t, name: &CStr) -> *mut Agraph_t {
+        let found = unsafe { agsubg(parent, name.as_ptr() as *mut c_char, 0) };
+        if !found.is_null() {
+            return found;
+        }
+        let mut child = unsafe { agfstsubg(parent) };
+        while !child.is_null() {
+            let found = find(child, name);
+            if !found.is_null() {
+                return found;
+            }
+            child = unsafe { agnxtsubg(child) };
+        }
+        std::ptr::null_mut()
+    }
+    let c_name = CString::new(name)?;
+    let raw = find(parent, &c_name);
+    if raw.is_null() {
+        return Err(Error::ClusterNotFound(name.to_string()));
+    }
+    Ok(raw)
+}
+
+/// A read-only view of a subgraph or cluster, see [`Graph::subgraphs`] and [`Graph::clusters`].
+#[derive(Debug, Clone, Copy)]
+pub struct SubgraphRef<'g> {
+    raw: *mut Agraph_t,
+    _graph: PhantomData<&'g ()>,
+}
+
+impl<'g> SubgraphRef<'g> {
+    /// the full name of the subgraph, including a `cluster_` prefix
+    pub fn name(&self) -> String {
+        unsafe { object_name(self.raw as *mut _) }
+    }
+
+    /// returns `true` if layout engines draw this subgraph as a box, i.e. its name starts with `cluster`
+    pub fn is_cluster(&self) -> bool {
+        self.name()
+            .get(..7)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("cluster"))
+    }
+
+    /// Iterates over the nodes in this subgraph and the subgraphs nested in it.
+    pub fn nodes(&self) -> Nodes<'g> {
+        Nodes {
+            graph: self.raw,
+            next: unsafe { agfstnode(self.raw) },
+            _graph: PhantomData,
+        }
+    }
+
+    /// Iterates over the edges in this subgraph and the subgraphs nested in it.
+    pub fn edges(&self) -> Edges<'g> {
+        let node = unsafe { agfstnode(self.raw) };
+        let next = if node.is_null() { std::ptr::null_mut() } else { unsafe { agfstout(self.raw, node) } };
+        Edges::new(self.raw, EdgeWalk::Graph, node, next)
+    }
+
+    /// Iterates over the subgraphs directly nested in this one.
+    pub fn subgraphs(&self) -> Subgraphs<'g> {
+        Subgraphs::new(self.raw)
+    }
+
+    /// returns `true` if the node `name` is a member of this subgraph
+    pub fn contains_node(&self, name: &str) -> bool {
+        let Ok(c_name) = CString::new(name) else {
+            return false;
+        };
+        !unsafe { agnode(self.raw, c_name.as_ptr() as *mut c_char, 0) }.is_null()
+    }
+
+    /// Reads the subgraph attribute `attr`, inherited from the root graph unless set on the subgraph.
+    pub fn get_attr(&self, attr: &str) -> Result<Option<String>, Error> {
+        get_attr_on_ptr(self.raw as *mut libc::c_void, attr)
+    }
+
+    /// Reads the subgraph attribute `attr` as a [`ClusterAttribute`].
+    ///
+    /// # Errors
+    /// Returns [`Error::ParseFailed`] if the value is not understood by [`ClusterAttribute`].
+    pub fn get_cluster_attribute(&self, attr: &str) -> Result<Option<ClusterAttribute>, Error> {
+        self.get_attr(attr)?
+            .map(|value| ClusterAttribute::try_from((attr, value.as_str())))
+            .transpose()
+    }
+}
+
+/// Iterator over subgraphs, see [`Graph::subgraphs`].
+pub struct Subgraphs<'g> {
+    next: *mut Agraph_t,
+    _graph: PhantomData<&'g ()>,
+}
+
+impl Subgraphs<'_> {
+    fn new(parent: *mut Agraph_t) -> Self {
+        Self {
+            next: unsafe { agfstsubg(parent) },
+            _graph: PhantomData,
+        }
+    }
+}
+
+impl<'g> Iterator for Subgraphs<'g> {
+    type Item = SubgraphRef<'g>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+        let subgraph = SubgraphRef {
+            raw: self.next,
+            _graph: PhantomData,
+        };
+        self.next = unsafe { agnxtsubg(self.next) };
+        Some(subgraph)
+    }
+}
+
+/// A subgraph or cluster inside a [`Graph`].
+///
+/// Graphviz owns subgraphs through their root graph, so this handle only borrows the [`Graph`]
+/// and never closes the subgraph, dropping it leaves the cluster in place. While a handle is
+/// alive the root graph can't be used or dropped:
+///
+/// ```compile_fail
+/// use wrappedviz::cgraph::{Context, Graph};
+///
+/// let ctx = Context::new();
+/// let mut graph = Graph::parse("digraph G {}", &ctx).unwrap();
+/// let cluster = graph.add_cluster("a").unwrap();
+/// drop(graph);
+/// cluster.name();
+/// ```
+pub struct Subgraph<'g, 'c> {
+    raw: *mut Agraph_t,
+    root: &'g mut Graph<'c>,
+}
+
+impl<'g, 'c> Subgraph<'g, 'c> {
+    /// the full name of the subgraph, including the `cluster_` prefix
+    pub fn name(&self) -> String {
+        unsafe { object_name(self.raw as *mut _) }
+    }
+
+    /// Adds a node to this subgraph, creating it in the root graph if it doesn't exist yet.
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if graphviz could not create the node.
+    pub fn add_node<S: AsRef<str>>(&mut self, name: S) -> Result<(), Error> {
+        let name = name.as_ref();
+        let cname = CString::new(name)?;
+        let raw = unsafe { agnode(self.raw, cname.as_ptr() as *mut c_char, 1) };
+        if raw.is_null() {
+            return Err(Error::NodeNotFound(name.to_string()));
+        }
+        self.root.nodes.insert(name.to_string(), Node { raw });
+        self.root.mark_layout_stale();
+        Ok(())
+    }
+
+    /// Adds a new edge between two existing nodes to this subgraph, making both nodes members.
+    ///
+    /// The edge is stored in the root graph under `name` like [`Graph::add_edge`] does.
+    ///
+    /// # Errors
+    /// - [`Error::NodeNotFound`] if `tail` or `head` does not exist.
+    /// - [`Error::EdgeNotFound`] if graphviz could not create the edge, e.g. in a strict graph.
+    pub fn add_edge<S: AsRef<str>>(&mut self, tail: S, head: S, name: S) -> Result<String, Error> {
+        let tail = self.root.node_ptr(tail.as_ref())?;
+        let head = self.root.node_ptr(head.as_ref())?;
+        let name = name.as_ref();
+        let c_name = CString::new(name)?;
+        let raw = unsafe { agedge(self.raw, tail, head, c_name.as_ptr() as *mut c_char, 1) };
+        if raw.is_null() {
+            return Err(Error::EdgeNotFound(name.to_string()));
+        }
+        self.root.edges.insert(name.to_string(), Edge { raw });
+        self.root.mark_layout_stale();
+        Ok(name.to_string())
+    }
+
+    /// Makes the existing edge stored under `key` and its nodes members of this subgraph.
+    ///
+    /// # Errors
+    /// Returns [`Error::EdgeNotFound`] if no edge with `key` exists.
+    pub fn include_edge(&mut self, key: &str) -> Result<(), Error> {
+        let edge = self
+            .root
+            .edges
+            .get(key)
+            .ok_or_else(|| Error::EdgeNotFound(key.to_string()))?;
+        let raw = unsafe { agsubedge(self.raw, edge.raw, 1) };
+        if raw.is_null() {
+            return Err(Error::EdgeNotFound(key.to_string()));
+        }
+        self.root.mark_layout_stale();
+        Ok(())
+    }
+
+    /// Sets a typed cluster attribute, e.g. [`ClusterAttr::Style`](crate::style::ClusterAttr::Style)
+    /// or a [`CommonAttr::Label`](crate::style::CommonAttr::Label).
+    pub fn set_cluster_attr<A: Into<ClusterAttribute>>(&mut self, attr: A) -> Result<(), Error> {
+        self.set_attr(attr.into())
+    }
+
+    /// Iterates over the nodes in this subgraph and the subgraphs nested in it.
+    pub fn nodes(&self) -> Nodes<'_> {
+        self.view().nodes()
+    }
+
+    /// Iterates over the edges in this subgraph and the subgraphs nested in it.
+    pub fn edges(&self) -> Edges<'_> {
+        self.view().edges()
+    }
+
+    /// Iterates over the subgraphs directly nested in this one.
+    pub fn subgraphs(&self) -> Subgraphs<'_> {
+        self.view().subgraphs()
+    }
+
+    /// returns `true` if the node `name` is a member of this subgraph
+    pub fn contains_node(&self, name: &str) -> bool {
+        self.view().contains_node(name)
+    }
+
+    /// Reads the subgraph attribute `attr`, inherited from the root graph unless set on the subgraph.
+    pub fn get_attr(&self, attr: &str) -> Result<Option<String>, Error> {
+        self.view().get_attr(attr)
+    }
+
+    fn view(&self) -> SubgraphRef<'_> {
+        SubgraphRef {
+            raw: self.raw,
+            _graph: PhantomData,
+        }
+    }
+
+    /// Set an attribute on the subgraph itself, e.g. the cluster's label or color.
+    pub fn set_attr<A: Attribute>(&mut self, attr: A) -> Result<(), Error> {
+        let (name_cstr, value_cstr) = attr.to_cstrings();
+        set_attr_on_ptr(self.raw as *mut libc::c_void, &name_cstr, &value_cstr, attr.is_html())?;
+        self.root.mark_layout_stale();
+        Ok(())
+    }
+
+    /// Creates a cluster nested in this subgraph, or returns the existing one.
+    pub fn add_cluster<S: AsRef<str>>(&mut self, name: S) -> Result<Subgraph<'_, 'c>, Error> {
+        let cluster_name = format!("cluster_{}", name.as_ref());
+        let raw = create_subgraph(self.raw, &cluster_name)?;
+        self.root.mark_layout_stale();
+        Ok(Subgraph { raw, root: self.root })
+    }
+}
+
+/// Declares the attribute `name` for all objects of `kind` with `value` as its default.
+///
+/// graphviz copies defaults into every object when they are created, so on the root graph
+/// objects that still hold the previous default are updated too. Other values are left alone,
+/// an explicit value equal to the previous default can't be told apart and is updated as well.
+///
+/// On a subgraph the default is local like `node [..]` inside a DOT subgraph: it only applies
+/// to objects created in the subgraph later, and the attribute is declared at the root with an
+/// empty default if it is missing there, since cgraph would otherwise declare it globally.
+fn set_default_attr(graph: *mut Agraph_t, kind: u32, name: &CString, value: &CString, html: bool) -> Result<(), Error> {
+    let kind = kind as c_int;
+    let name_ptr = name.as_ptr() as *mut c_char;
+    let declare = if html { agattr_html } else { agattr_text };
+
+    let root = unsafe { agroot(graph as *mut _) };
+    if root != graph {
+        let declared = unsafe { agattr_text(root, kind, name_ptr, std::ptr::null()) };
+        if declared.is_null() && unsafe { agattr_text(root, kind, name_ptr, c"".as_ptr()) }.is_null() {
+            return Err(attribute_failed(name, value));
+        }
+        if unsafe { declare(graph, kind, name_ptr, value.as_ptr()) }.is_null() {
+            return Err(attribute_failed(name, value));
+        }
+        return Ok(());
+    }
+
+    let previous = unsafe { agattr_text(graph, kind, name_ptr, std::ptr::null()) };
+    let previous = (!previous.is_null())
+        .then(|| unsafe { CStr::from_ptr((*previous).defval) }.to_owned());
+
+    let sym = unsafe { declare(graph, kind, name_ptr, value.as_ptr()) };
+    if sym.is_null() {
+        return Err(attribute_failed(name, value));
+    }
+    let Some(previous) = previous else {
+        // a new declaration already gave every existing object the default
+        return Ok(());
+    };
+    let update = |obj: *mut libc::c_void| unsafe {
+        if CStr::from_ptr(agxget(obj, sym)) == previous.as_c_str() {
+            if html {
+                agxset_html(obj, sym, value.as_ptr());
+            } else {
+                agxset_text(obj, sym, value.as_ptr());
+            }
+        }
+    };
+    unsafe {
+        let mut n = agfstnode(graph);
+        while !n.is_null() {
+            if kind == AGNODE as c_int {
+                update(n as *mut _);
+            } else if kind == AGEDGE as c_int {
+                let mut e = agfstout(graph, n);
+                while !e.is_null() {
+                    update(e as *mut _);
+                    e = agnxtout(graph, e);
+                }
+            }
+            n = agnxtnode(graph, n);
+        }
+    }
+    Ok(())
+}
+
+fn get_attr_on_ptr(obj: *mut libc::c_void, attr: &str) -> Result<Option<String>, Error> {
+    let name = CString::new(attr)?;
+    let value = unsafe { agget(obj, name.as_ptr() as *mut c_char) };
+    Ok(unsafe { attr_value(value) })
+}
+
+/// graphviz returns null for undeclared attributes and `""` for declared but unset ones
+unsafe fn attr_value(value: *const c_char) -> Option<String> {
+    if value.is_null() {
+        return None;
+    }
+    let value = unsafe { CStr::from_ptr(value) }.to_string_lossy();
+    (!value.is_empty()).then(|| value.into_owned())
+}
+
+/// Iterator over the `(name, value)` pairs of the attributes set on a graph, node or edge.
+///
+/// Walks the attributes declared for that kind of object with `agnxtattr`,
+/// skipping the ones without a value on this object.
+pub struct Attributes<'g> {
+    graph: *mut Agraph_t,
+    kind: c_int,
+    obj: *mut libc::c_void,
+    sym: *mut Agsym_t,
+    _graph: std::marker::PhantomData<&'g Graph<'g>>,
+}
+
+impl<'g> Attributes<'g> {
+    fn new(graph: &'g Graph<'_>, kind: u32, obj: *mut libc::c_void) -> Self {
+        Self {
+            graph: graph.graph,
+            kind: kind as c_int,
+            obj,
+            sym: std::ptr::null_mut(),
+            _graph: std::marker::PhantomData,
+        }
+    }
+}
+
+impl Iterator for Attributes<'_> {
+    type Item = (String, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.sym = unsafe { agnxtattr(self.graph, self.kind, self.sym) };
+            if self.sym.is_null() {
+                return None;
+            }
+            let sym = unsafe { &*self.sym };
+            let Some(value) = (unsafe { attr_value(agxget(self.obj, self.sym)) }) else {
+                continue;
+            };
+            let name = unsafe { CStr::from_ptr(sym.name) }.to_string_lossy().into_owned();
+            return Some((name, value));
+        }
+    }
+}
+
+fn attribute_failed(name: &CString, value: &CString) -> Error {
+    Error::AttributeFailed {
+        name: name.to_string_lossy().into_owned(),
+        value: value.to_string_lossy().into_owned(),
+    }
+}
+
+/// makes sure the port named in a `tailport` or `headport` value exists in the node's label
+fn check_port(node: *mut Agnode_t, port: &CStr) -> Result<(), Error> {
+    let port = port.to_string_lossy();
+    let Some(name) = port.parse::<Port>().ok().and_then(|port| port.name) else {
+        return Ok(());
+    };
+    let label_name = CString::new("label").unwrap();
+    let label = unsafe { agget(node as *mut _, label_name.as_ptr() as *mut c_char) };
+    let ports = if label.is_null() {
+        Vec::new()
+    } else {
+        let html = unsafe { aghtmlstr(label) } != 0;
+        label_ports(&unsafe { CStr::from_ptr(label) }.to_string_lossy(), html)
+    };
+    if ports.contains(&name) {
+        Ok(())
+    } else {
+        Err(Error::PortNotFound {
+            node: unsafe { object_name(node as *mut _) },
+            port: name,
+        })
+    }
+}
+
+/// sets an attribute, `html` values are stored with `agstrdup_html` so graphviz parses them as markup
+fn set_attr_on_ptr(obj: *mut libc::c_void, name: &CString, value: &CString, html: bool) -> Result<(), Error> {
+    let def = CString::new("").unwrap();
+    let set = if html { agsafeset_html } else { agsafeset_text };
+    let ret = unsafe {
+        set(
+            obj,
+            name.as_ptr() as *mut i8,
+            value.as_ptr(),
+            def.as_ptr(),
+        )
+    };
+    if ret != 0 {
+        Err(attribute_failed(name, value))
+    } else {
+        Ok(())
+    }
+}
+
+const POINTS_PER_INCH: f64 = 72.0;
+
+/// the record graphviz attached to a graph, node or edge, see `AGDATA`.
+/// after a layout this is the `Agraphinfo_t`, `Agnodeinfo_t` or `Agedgeinfo_t` of the object.
+unsafe fn agdata<T>(obj: *mut Agobj_t) -> *mut T {
+    unsafe { (*obj).data as *mut T }
+}
+
+unsafe fn object_name(obj: *mut libc::c_void) -> String {
+    let name_ptr = unsafe { agnameof(obj) };
+    if name_ptr.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(name_ptr) }.to_string_lossy().into_owned()
+}
+
+/// `fallback` is used as position for labels the layout did not place itself,
+/// node labels are drawn at the node's center.
+unsafe fn label_layout(label: *const textlabel_t, fallback: Option<Point>) -> Option<LabelLayout> {
+    let label = unsafe { label.as_ref()? };
+    let position = if label.set { label.pos.into() } else { fallback? };
+    let text = if label.text.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(label.text) }.to_string_lossy().into_owned()
+    };
+    Some(LabelLayout {
+        text,
+        position,
+        width: label.dimen.x,
+        height: label.dimen.y,
+    })
+}
+
+unsafe fn spline_layout(spl: *const splines) -> Vec<Spline> {
+    let Some(spl) = (unsafe { spl.as_ref() }) else {
+        return Vec::new();
+    };
+    if spl.list.is_null() {
+        return Vec::new();
+    }
+    let beziers = unsafe { std::slice::from_raw_parts(spl.list, spl.size) };
+    beziers
+        .iter()
+        .map(|bz| {
+            let points = if bz.list.is_null() {
+                &[][..]
+            } else {
+                unsafe { std::slice::from_raw_parts(bz.list, bz.size) }
+            };
+            Spline {
+                points: points.iter().map(|&p| p.into()).collect(),
+                start_arrow: (bz.sflag != 0).then(|| bz.sp.into()),
+                end_arrow: (bz.eflag != 0).then(|| bz.ep.into()),
+            }
+        })
+        .collect()
+}
+
+/// formats a coordinate or size like graphviz's `%.5g`, without trailing zeros
+fn layout_number(value: f64) -> String {
+    let formatted = format!("{:.5}", value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    if trimmed == "-0" { "0".to_string() } else { trimmed.to_string() }
+}
+
+fn layout_point(point: Point) -> String {
+    format!("{},{}", layout_number(point.x), layout_number(point.y))
+}
+
+/// the computed `pos`, `width` or `height` of a node, read from its `Agnodeinfo_t`
+unsafe fn node_layout_attr(node: *mut Agnode_t, attr: &str) -> Option<String> {
+    let info = unsafe { agdata::<Agnodeinfo_t>(node as *mut _).as_ref()? };
+    match attr {
+        "pos" => Some(layout_point(info.coord.into())),
+        "width" => Some(layout_number(info.width)),
+        "height" => Some(layout_number(info.height)),
+        _ => None,
+    }
+}
+
+/// the computed `pos` or `lp` of an edge, read from its `Agedgeinfo_t`
+unsafe fn edge_layout_attr(edge: *mut Agedge_t, attr: &str) -> Option<String> {
+    let info = unsafe { agdata::<Agedgeinfo_t>(edge as *mut _).as_ref()? };
+    match attr {
+        "pos" => {
+            let splines = unsafe { spline_layout(info.spl) };
+            if splines.is_empty() {
+                return None;
+            }
+            let splines: Vec<String> = splines
+                .into_iter()
+                .map(|spline| {
+                    let mut parts = Vec::new();
+                    if let Some(start) = spline.start_arrow {
+                        parts.push(format!("s,{}", layout_point(start)));
+                    }
+                    if let Some(end) = spline.end_arrow {
+                        parts.push(format!("e,{}", layout_point(end)));
+                    }
+                    parts.extend(spline.points.into_iter().map(layout_point));
+                    parts.join(" ")
+                })
+                .collect();
+            Some(splines.join(";"))
+        }
+        "lp" => unsafe { label_layout(info.label, None) }.map(|label| layout_point(label.position)),
+        _ => None,
+    }
+}
+
+/// the computed `bb` or `lp` of a graph, read from its `Agraphinfo_t`
+unsafe fn graph_layout_attr(graph: *mut Agraph_t, attr: &str) -> Option<String> {
+    let info = unsafe { agdata::<Agraphinfo_t>(graph as *mut _).as_ref()? };
+    match attr {
+        "bb" => Some(format!(
+            "{},{}",
+            layout_point(info.bb.LL.into()),
+            layout_point(info.bb.UR.into())
+        )),
+        "lp" => unsafe { label_layout(info.label, None) }.map(|label| layout_point(label.position)),
+        _ => None,
+    }
+}
+
+impl From<pointf> for Point {
+    fn from(p: pointf) -> Self {
+        Point::new(p.x, p.y)
+    }
+}
+
+impl From<boxf> for BoundingBox {
+    fn from(b: boxf) -> Self {
+        BoundingBox {
+            lower_left: b.LL.into(),
+            upper_right: b.UR.into(),
+        }
+    }
+}
+
+impl<'c> std::ops::Drop for Graph<'c> {
+    fn drop(&mut self) {
+        unsafe {
+            // the layout records hang off the graph and must be released before it is closed
+            if self.layout_state != LayoutState::None {
+                gvFreeLayout(self.ctx.ctx, self.graph);
+            }
+            // closes all subgraphs as well, `Subgraph` handles never close them
+            agclose(self.graph);
+        }
+    }
+}
+
+thread_local! {
+    /// messages graphviz reported on this thread since the last `Context::capture`
+    static DIAGNOSTICS: RefCell<Vec<Diagnostic>> = const { RefCell::new(Vec::new()) };
+    /// graphviz hands messages over in fragments (`"Error"`, `": "`, `"msg\n"`), buffered until a newline
+    static PENDING: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// installed through `agseterrf` so graphviz messages end up in `DIAGNOSTICS` instead of stderr.
+unsafe extern "C" fn collect_diagnostic(message: *mut c_char) -> c_int {
+    if message.is_null() {
+        return 0;
+    }
+    let fragment = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        pending.push_str(&fragment);
+        while let Some(idx) = pending.find('\n') {
+            let line: String = pending.drain(..=idx).collect();
+            push_diagnostic(&line);
+        }
+    });
+    fragment.len() as c_int
+}
+
+fn push_diagnostic(line: &str) {
+    if line.trim().is_empty() {
+        return;
+    }
+    DIAGNOSTICS.with(|diagnostics| {
+        let mut diagnostics = diagnostics.borrow_mut();
+        let previous = diagnostics.last().map_or(Severity::Error, |d| d.severity);
+        diagnostics.push(Diagnostic::parse(line, previous));
+    });
+}
+
+/// Created from `sys::gvContext()` this struct is used to initialize graphs
+pub struct Context {
+    ctx: *mut GVC_t,
+    /// diagnostics reported by graphviz during the most recent call made through this context
+    diagnostics: RefCell<Vec<Diagnostic>>,
+    /// renderers added with `add_renderer`, freed after `gvFreeContext`
+    renderers: RefCell<Vec<*mut RendererSlot>>,
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Self::from_gvc(unsafe { gvContext() })
+    }
+
+    /// Creates a context with the core, dot and neato plugins registered statically, for systems
+    /// where graphviz has no plugin config (`config6`) to load them from, e.g. minimal containers.
+    ///
+    /// Plugins listed in a config are still loaded when one exists.
+    /// Use [`check_plugins`](Self::check_plugins) to verify the result.
+    #[cfg(feature = "builtins")]
+    pub fn with_builtins() -> Context {
+        let ctx = Self::from_gvc(unsafe { gvContextPlugins((&raw const rust_builtins).cast(), 1) });
+        debug!(
+            layouts = ?ctx.plugins(PluginKind::Layout),
+            devices = ?ctx.plugins(PluginKind::Device),
+            "created context with builtin plugins"
+        );
+        ctx
+    }
+
+    fn from_gvc(gvc: *mut GVC_t) -> Context {
+        unsafe {
+            agseterrf(Some(collect_diagnostic));
+        }
+        Self {
+            ctx: gvc,
+            diagnostics: RefCell::new(Vec::new()),
+            renderers: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The names of the plugins of `kind` graphviz loaded, e.g. `"dot"` for [`PluginKind::Layout`].
+    pub fn plugins(&self, kind: PluginKind) -> Vec<String> {
+        let kind = CString::new(kind.to_string()).unwrap();
+        let mut count: c_int = 0;
+        unsafe {
+            let list = gvPluginList(self.ctx, kind.as_ptr(), &mut count);
+            if list.is_null() {
+                return Vec::new();
+            }
+            let mut plugins = Vec::with_capacity(count as usize);
+            for i in 0..count as usize {
+                let item = *list.add(i);
+                plugins.push(CStr::from_ptr(item).to_string_lossy().into_owned());
+                libc::free(item as *mut libc::c_void);
+            }
+            libc::free(list as *mut libc::c_void);
+            plugins
+        }
+    }
+
+    /// The version of the graphviz library in use, e.g. `"12.2.1"`.
+    pub fn version(&self) -> String {
+        unsafe {
+            let version = gvcVersion(self.ctx);
+            if version.is_null() {
+                return String::new();
+            }
+            CStr::from_ptr(version).to_string_lossy().into_owned()
+        }
+    }
+
+    /// The layout engines the installed plugins provide, e.g. `sfdp` is missing without GTS.
+    pub fn available_layouts(&self) -> Vec<Layout> {
+        self.plugins(PluginKind::Layout)
+            .iter()
+            .filter_map(|name| name.parse().ok())
+            .collect()
+    }
+
+    /// The output formats the installed plugins provide, e.g. `pdf` and `gd` need cairo and libgd.
+    ///
+    /// Formats added with [`add_renderer`](Self::add_renderer) are not [`OutputFormat`]s and
+    /// are left out.
+    pub fn available_formats(&self) -> Vec<OutputFormat> {
+        self.plugins(PluginKind::Device)
+            .iter()
+            .filter_map(|name| name.parse().ok())
+            .collect()
+    }
+
+    /// Checks that graphviz loaded layout, render and device plugins, without which nothing
+    /// can be laid out or rendered.
+    ///
+    /// # Errors
+    /// Returns [`Error::PluginsMissing`] with the kinds of which no plugin was loaded.
+    pub fn check_plugins(&self) -> Result<(), Error> {
+        let missing: Vec<PluginKind> = [PluginKind::Layout, PluginKind::Render, PluginKind::Device]
+            .into_iter()
+            .filter(|kind| self.plugins(*kind).is_empty())
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::PluginsMissing(missing))
+        }
+    }
+
+    /// All warnings and errors graphviz reported during the most recent parse, layout or render.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.borrow().clone()
+    }
+
+    /// Warnings graphviz reported during the most recent parse, layout or render.
+    ///
+    /// Useful after a call that succeeded, since errors are attached to the returned [`Error`] instead.
+    pub fn warnings(&self) -> Vec<Diagnostic> {
+        self.diagnostics
+            .borrow()
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .cloned()
+            .collect()
+    }
+
+    /// Runs `f` and collects everything graphviz reported through `agerr` while it ran.
+    ///
+    /// The collected messages are stored on the context (see [`Context::diagnostics`]) and returned
+    /// alongside the result so they can be attached to an [`Error`].
+    fn capture<T>(&self, f: impl FnOnce() -> T) -> (T, Vec<Diagnostic>) {
+        DIAGNOSTICS.with(|d| d.borrow_mut().clear());
+        PENDING.with(|p| p.borrow_mut().clear());
+        unsafe {
+            agreseterrors();
+        }
+
+        let result = f();
+
+        let pending = PENDING.with(|p| std::mem::take(&mut *p.borrow_mut()));
+        push_diagnostic(&pending);
+        let mut diagnostics = DIAGNOSTICS.with(|d| std::mem::take(&mut *d.borrow_mut()));
+
+        // messages below the `agseterr` threshold are not passed to the callback, only logged
+        if diagnostics.is_empty() && unsafe { agerrors() } != 0 {
+            let last = unsafe { aglasterr() };
+            if !last.is_null() {
+                let message = unsafe { CStr::from_ptr(last) }.to_string_lossy().into_owned();
+                unsafe { libc::free(last as *mut libc::c_void) };
+                for line in message.lines().filter(|l| !l.trim().is_empty()) {
+                    let previous = diagnostics.last().map_or(Severity::Error, |d: &Diagnostic| d.severity);
+                    diagnostics.push(Diagnostic::parse(line, previous));
+                }
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        for diagnostic in &diagnostics {
+            tracing::warn!(severity = ?diagnostic.severity, line = ?diagnostic.line, "graphviz: {}", diagnostic.message);
+        }
+        *self.diagnostics.borrow_mut() = diagnostics.clone();
+        (result, diagnostics)
+    }
+
+    /// Renders the provided `graph` into the specified `format` using Graphviz.
+    ///
+    /// # Arguments
+    /// - `graph`: The graph to render.
+    /// - `format`: The desired output format (e.g., `OutputFormat::Svg`, `OutputFormat::Png`).
+    ///
+    /// # Returns
+    /// A `Vec<u8>` containing the rendered graph output (e.g., SVG or image bytes).
+    ///
+    /// # Errors
+    /// - [`Error::NotLaidOut`] or [`Error::StaleLayout`] if `graph` has no up to date layout,
+    ///   see [`Graph::layout`] and [`Graph::relayout`].
+    /// - [`Error::UnsupportedFormat`] if no installed plugin renders `format`.
+    /// - [`Error::RenderFailed`] if graphviz fails to render the requested format.
+    pub fn render(&self, graph: &Graph, format: OutputFormat) -> Result<Vec<u8>, Error> {
+        graph.check_layout()?;
+        let format_cstr = CString::new(format.to_string())?;
+        trace!(graph = %graph.name(), %format, "rendering graph");
+        let output = self
+            .render_data(graph, &format_cstr)
+            .map_err(|diagnostics| self.render_failed(format, diagnostics))?;
+        debug!(graph = %graph.name(), %format, bytes = output.len(), "rendered graph");
+        Ok(output)
+    }
+
+    /// the error for a failed render, telling a missing plugin apart from other failures
+    fn render_failed(&self, format: OutputFormat, diagnostics: Vec<Diagnostic>) -> Error {
+        let available = self.available_formats();
+        if available.contains(&format) {
+            Error::RenderFailed { format, diagnostics }
+        } else {
+            Error::UnsupportedFormat { format, available }
+        }
+    }
+
+    /// renders with `gvRenderData`, returning graphviz's diagnostics if it fails
+    fn render_data(&self, graph: &Graph, format: &CStr) -> Result<Vec<u8>, Vec<Diagnostic>> {
+        let result_str = CString::new("").unwrap();
+        let mut result_ptr: *mut std::os::raw::c_char = result_str.as_ptr() as *mut std::os::raw::c_char;
+        let mut length: usize = 0;
+
+        unsafe {
+            let (ret, diagnostics) = self.capture(|| {
+                gvRenderData(self.ctx, graph.graph, format.as_ptr(), &mut result_ptr, &mut length as *mut usize)
+            });
+            if ret != 0 {
+                return Err(diagnostics);
+            }
+            let output = std::slice::from_raw_parts(result_ptr as *const u8, length).to_vec();
+            gvFreeRenderData(result_ptr);
+            Ok(output)
+        }
+    }
+
+    /// Registers `renderer` as the output format `format`, rendered with [`render_custom`](Self::render_custom).
+    ///
+    /// The renderer lives as long as the context, see [`crate::render`].
+    ///
+    /// # Errors
+    /// - [`Error::PluginFailed`] if a renderer for `format` was already added or graphviz is out of memory.
+    /// - [`Error::InteriorNul`] if `format` contains a NUL byte.
+    pub fn add_renderer<R: Renderer + 'static>(&self, format: &str, renderer: R) -> Result<(), Error> {
+        let mut renderers = self.renderers.borrow_mut();
+        if renderers.iter().any(|&slot| unsafe { &*slot }.format() == format) {
+            return Err(Error::PluginFailed(format.to_string()));
+        }
+        renderers.push(RendererSlot::register(self.ctx, format, Box::new(renderer))?);
+        debug!(format, "added renderer");
+        Ok(())
+    }
+
+    /// Renders `graph` with the renderer added for `format` by [`add_renderer`](Self::add_renderer),
+    /// or any other format graphviz knows by name.
+    ///
+    /// # Errors
+    /// - [`Error::NotLaidOut`] or [`Error::StaleLayout`] if `graph` has no up to date layout.
+    /// - [`Error::Io`] with the first error the renderer returned.
+    /// - [`Error::CustomRenderFailed`] if graphviz fails to render `format`, e.g. because it is unknown.
+    pub fn render_custom(&self, graph: &Graph, format: &str) -> Result<Vec<u8>, Error> {
+        graph.check_layout()?;
+        let format_cstr = CString::new(format)?;
+        trace!(graph = %graph.name(), format, "rendering graph with custom renderer");
+        let output = self.render_data(graph, &format_cstr);
+        let failed = self
+            .renderers
+            .borrow()
+            .iter()
+            .map(|&slot| unsafe { &mut *slot })
+            .find(|slot| slot.format() == format)
+            .and_then(|slot| slot.take_error());
+        if let Some(e) = failed {
+            return Err(Error::Io(e));
+        }
+        let output = output.map_err(|diagnostics| Error::CustomRenderFailed {
+            format: format.to_string(),
+            diagnostics,
+        })?;
+        debug!(graph = %graph.name(), format, bytes = output.len(), "rendered graph");
+        Ok(output)
+    }
+
+    /// Renders `graph` into each of `formats` in turn, laying it out once for all of them.
+    ///
+    /// All formats share the one layout, e.g. to produce an SVG, a PNG and the matching `cmapx`
+    /// image map. A graph with an up to date layout is rendered as it is. Otherwise a copy is laid
+    /// out with the graph's [`configured_layout`](Graph::configured_layout), or `dot` if it has
+    /// none, so the caller's graph and its layout state are left untouched.
+    ///
+    /// A format that fails to render does not stop the others, its entry holds the error instead.
+    /// If the layout fails every entry holds [`Error::LayoutFailed`].
+    pub fn render_many(
+        &self,
+        graph: &Graph,
+        formats: &[OutputFormat],
+    ) -> HashMap<OutputFormat, Result<Vec<u8>, Error>> {
+        let copy = match graph.layout_state {
+            LayoutState::Current => None,
+            _ => Some(self.laid_out_copy(graph)),
+        };
+        formats
+            .iter()
+            .map(|&format| {
+                let output = match &copy {
+                    None => self.render(graph, format),
+                    Some(Ok(copy)) => self.render(copy, format),
+                    Some(Err((code, diagnostics))) => {
+                        Err(Error::LayoutFailed { code: *code, diagnostics: diagnostics.clone() })
+                    }
+                };
+                (format, output)
+            })
+            .collect()
+    }
+
+    /// copies `graph` and lays the copy out for `render_many`, returning what `LayoutFailed` needs
+    fn laid_out_copy<'c>(&'c self, graph: &Graph) -> Result<Graph<'c>, (i32, Vec<Diagnostic>)> {
+        let engine = graph.configured_layout().unwrap_or(Layout::Dot);
+        let mut copy = graph.duplicate(self).map_err(|e| match e {
+            Error::InvalidDot(diagnostics) => (-1, diagnostics),
+            other => (-1, vec![Diagnostic::parse(&format!("Error: {}", other), Severity::Error)]),
+        })?;
+        copy.layout(engine).map_err(|e| match e {
+            Error::LayoutFailed { code, diagnostics } => (code, diagnostics),
+            other => (-1, vec![Diagnostic::parse(&format!("Error: {}", other), Severity::Error)]),
+        })?;
+        Ok(copy)
+    }
+
+    /// Renders `graph` in `format` straight into `writer`, without collecting the output in memory.
+    ///
+    /// Useful for large PNG/PDF renders or streaming into an HTTP response.
+    /// On platforms without custom C streams (anything but glibc, macOS and the BSDs)
+    /// this falls back to [`render`](Self::render) followed by a single `write_all`.
+    ///
+    /// # Errors
+    /// - [`Error::NotLaidOut`] or [`Error::StaleLayout`] if `graph` has no up to date layout.
+    /// - [`Error::UnsupportedFormat`] if no installed plugin renders `format`.
+    /// - [`Error::RenderFailed`] if graphviz fails to render the requested format.
+    /// - [`Error::Io`] if writing to `writer` fails, rendering stops at the first failed write.
+    pub fn render_to_writer<W: Write>(
+        &self,
+        graph: &Graph,
+        format: OutputFormat,
+        mut writer: W,
+    ) -> Result<(), Error> {
+        graph.check_layout()?;
+        let format_cstr = CString::new(format.to_string())?;
+        let mut sink = WriterSink { writer: &mut writer, bytes: 0, error: None };
+        let file = unsafe {
+            rust_open_writer(&mut sink as *mut WriterSink as *mut libc::c_void, Some(write_to_sink))
+        };
+        if file.is_null() {
+            let output = self.render(graph, format)?;
+            writer.write_all(&output)?;
+            return Ok(writer.flush()?);
+        }
+
+        trace!(graph = %graph.name(), %format, "rendering graph to writer");
+        let (ret, diagnostics) = self
+            .capture(|| unsafe { gvRender(self.ctx, graph.graph, format_cstr.as_ptr(), file) });
+        // closing flushes the last buffered chunk through `write_to_sink`
+        let closed = unsafe { fclose(file) };
+
+        if let Some(e) = sink.error.take() {
+            return Err(Error::Io(e));
+        }
+        if ret != 0 {
+            return Err(self.render_failed(format, diagnostics));
+        }
+        if closed != 0 {
+            return Err(Error::Io(std::io::Error::last_os_error()));
+        }
+        debug!(graph = %graph.name(), %format, bytes = sink.bytes, "rendered graph to writer");
+        Ok(writer.flush()?)
+    }
+
+    /// Renders `graph` in `format` into the file at `path`, creating or truncating it.
+    ///
+    /// # Errors
+    /// - [`Error::NotLaidOut`] or [`Error::StaleLayout`] if `graph` has no up to date layout.
+    /// - [`Error::UnsupportedFormat`] if no installed plugin renders `format`.
+    /// - [`Error::RenderFailed`] if graphviz fails to render the requested format
+    ///   or cannot open `path`, the diagnostics say which.
+    pub fn render_to_file<P: AsRef<Path>>(
+        &self,
+        graph: &Graph,
+        format: OutputFormat,
+        path: P,
+    ) -> Result<(), Error> {
+        graph.check_layout()?;
+        let format_cstr = CString::new(format.to_string())?;
+        let path_cstr = CString::new(path.as_ref().as_os_str().as_encoded_bytes())?;
+
+        trace!(graph = %graph.name(), %format, path = %path.as_ref().display(), "rendering graph to file");
+        let (ret, diagnostics) = self.capture(|| unsafe {
+            gvRenderFilename(self.ctx, graph.graph, format_cstr.as_ptr(), path_cstr.as_ptr())
+        });
+        if ret != 0 {
+            return Err(self.render_failed(format, diagnostics));
+        }
+        Ok(())
+    }
+}
+
+/// the cookie behind a stream from `rust_open_writer`
+struct WriterSink<'w> {
+    writer: &'w mut dyn Write,
+    bytes: usize,
+    /// the first write error, graphviz only sees that the write failed
+    error: Option<std::io::Error>,
+}
+
+unsafe extern "C" fn write_to_sink(
+    cookie: *mut libc::c_void,
+    buf: *const c_char,
+    len: usize,
+) -> ssize_t {
+    let sink = unsafe { &mut *(cookie as *mut WriterSink) };
+    if sink.error.is_some() {
+        return -1;
+    }
+    let data = unsafe { std::slice::from_raw_parts(buf as *const u8, len) };
+    match sink.writer.write_all(data) {
+        Ok(()) => {
+            sink.bytes += len;
+            len as ssize_t
+        }
+        Err(e) => {
+            sink.error = Some(e);
+            -1
+        }
+    }
+}
+
+/// The key an edge read by graphviz is stored under: its name if it has one
+/// (the DOT `key` attribute), `tail->head` otherwise, with `#2`, `#3`, ... appended
+/// to tell parallel edges apart.
+unsafe fn edge_key(edge: *mut Agedge_t, edge_op: &str, taken: &HashMap<String, Edge>) -> String {
+    let edge = unsafe { rust_agmkout(edge) };
+    let name_ptr = unsafe { agnameof(edge as *mut _) };
+    let name = (!name_ptr.is_null())
+        .then(|| unsafe { CStr::from_ptr(name_ptr) }.to_string_lossy().into_owned())
+        .filter(|name| !name.is_empty());
+    let base = name.unwrap_or_else(|| unsafe {
+        format!(
+            "{}{}{}",
+            object_name(rust_agtail(edge) as *mut _),
+            edge_op,
+            object_name(rust_aghead(edge) as *mut _)
+        )
+    });
+    if !taken.contains_key(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{}#{}", base, n))
+        .find(|key| !taken.contains_key(key))
+        .expect("there is always a free suffix")
+}
+
+/// A node visited while walking a [`Graph`].
+#[derive(Debug, Clone)]
+pub struct NodeRef<'g> {
+    name: String,
+    raw: *mut Agnode_t,
+    _graph: PhantomData<&'g ()>,
+}
+
+impl<'g> NodeRef<'g> {
+    unsafe fn from_raw(raw: *mut Agnode_t) -> Self {
+        Self {
+            name: unsafe { object_name(raw as *mut _) },
+            raw,
+            _graph: PhantomData,
+        }
+    }
+
+    /// the node's name, the key used by [`Graph::set_attr_on_node`] and friends
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq for NodeRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Eq for NodeRef<'_> {}
+
+/// An edge visited while walking a [`Graph`], with both of its endpoints.
+#[derive(Debug, Clone)]
+pub struct EdgeRef<'g> {
+    name: Option<String>,
+    tail: NodeRef<'g>,
+    head: NodeRef<'g>,
+    raw: *mut Agedge_t,
+}
+
+impl<'g> EdgeRef<'g> {
+    unsafe fn from_raw(raw: *mut Agedge_t) -> Self {
+        // the in-edge half of an edge has no name of its own
+        let raw = unsafe { rust_agmkout(raw) };
+        let name_ptr = unsafe { agnameof(raw as *mut _) };
+        let name = (!name_ptr.is_null())
+            .then(|| unsafe { CStr::from_ptr(name_ptr) }.to_string_lossy().into_owned());
+        Self {
+            name,
+            tail: unsafe { NodeRef::from_raw(rust_agtail(raw)) },
+            head: unsafe { NodeRef::from_raw(rust_aghead(raw)) },
+            raw,
+        }
+    }
+
+    /// the edge's name (its DOT `key`), `None` for anonymous edges
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// the node the edge starts at
+    pub fn tail(&self) -> &NodeRef<'g> {
+        &self.tail
+    }
+
+    /// the node the edge points to
+    pub fn head(&self) -> &NodeRef<'g> {
+        &self.head
+    }
+}
+
+impl PartialEq for EdgeRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Eq for EdgeRef<'_> {}
+
+/// Iterator over the nodes of a [`Graph`], see [`Graph::nodes`].
+pub struct Nodes<'g> {
+    graph: *mut Agraph_t,
+    next: *mut Agnode_t,
+    _graph: PhantomData<&'g ()>,
+}
+
+impl<'g> Iterator for Nodes<'g> {
+    type Item = NodeRef<'g>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+        let node = unsafe { NodeRef::from_raw(self.next) };
+        self.next = unsafe { agnxtnode(self.graph, self.next) };
+        Some(node)
+    }
+}
+
+/// which edges an [`Edges`] iterator walks
+#[derive(Debug, Clone, Copy)]
+enum EdgeWalk {
+    /// every edge of the graph, the out edges of one node after the other
+    Graph,
+    Out,
+    In,
+    /// in and out edges of one node
+    All,
+}
+
+/// Iterator over edges of a [`Graph`], see [`Graph::edges`] and [`Graph::out_edges`].
+pub struct Edges<'g> {
+    graph: *mut Agraph_t,
+    walk: EdgeWalk,
+    node: *mut Agnode_t,
+    next: *mut Agedge_t,
+    _graph: PhantomData<&'g ()>,
+}
+
+impl<'g> Edges<'g> {
+    fn new(graph: *mut Agraph_t, walk: EdgeWalk, node: *mut Agnode_t, next: *mut Agedge_t) -> Self {
+        Self {
+            graph,
+            walk,
+            node,
+            next,
+            _graph: PhantomData,
+        }
+    }
+}
+
+impl<'g> Iterator for Edges<'g> {
+    type Item = EdgeRef<'g>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            if let EdgeWalk::Graph = self.walk {
+                // move on to the next node with out edges
+                while self.next.is_null() && !self.node.is_null() {
+                    self.node = agnxtnode(self.graph, self.node);
+                    if !self.node.is_null() {
+                        self.next = agfstout(self.graph, self.node);
+                    }
+                }
+            }
+            if self.next.is_null() {
+                return None;
+            }
+            let edge = EdgeRef::from_raw(self.next);
+            self.next = match self.walk {
+                EdgeWalk::Graph | EdgeWalk::Out => agnxtout(self.graph, self.next),
+                EdgeWalk::In => agnxtin(self.graph, self.next),
+                EdgeWalk::All => agnxtedge(self.graph, self.next, self.node),
+            };
+            Some(edge)
+        }
+    }
+}
+
+/// wrapper struct for `sys::Agnode_t` for handling graph nodes
+#[derive(Clone)]
+pub struct Node {
+    raw: *mut Agnode_t,
+}
+
+
+
+/// wrapper struct for `sys::Agedge_t` for adding edges between nodes
+pub struct Edge {
+    raw: *mut Agedge_t,
+}
+
+impl std::ops::Drop for Context {
+    fn drop(&mut self) {
+        unsafe {
+            gvFreeContext(self.ctx);
+        }
+        for slot in self.renderers.get_mut().drain(..) {
+            unsafe { RendererSlot::free(slot) };
+        }
+    }
+}
+
+pub trait DotGraph {
+    type Err: std::error::Error;
+    fn generate_dot(&self) -> Result<String, Self::Err>;
+}
+
+pub trait GraphEngine {
+    type Err: std::error::Error;
     fn render_graph<G: DotGraph>(&self, graph: &G, format: OutputFormat) -> Result<String, Self::Err>;
 }
 
@@ -529,6 +2647,8 @@ impl GraphEngine for Context {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::style::shape::NodeShape;
+    use crate::style::{ClusterAttr, ClusterStyle, CommonAttr, RankDir};
     use std::ptr;
 
     /// Dummy Context for testing.
@@ -550,7 +2670,7 @@ mod tests {
             }
         "#;
 
-        let mut graph = Graph::new(dot, &ctx);
+        let mut graph = Graph::parse(dot, &ctx).unwrap();
 
         // Make sure nodes were created
         assert!(graph.nodes.contains_key("A"));
@@ -585,21 +2705,740 @@ mod tests {
         let ctx = dummy_context();
         let dot = "digraph G {}";
 
-        let graph = Graph::new(dot, &ctx);
+        let graph = Graph::parse(dot, &ctx).unwrap();
 
         assert!(graph.nodes.is_empty());
         assert!(graph.edges.is_empty());
     }
 
-    /*#[test]
-    fn test_graph_new_invalid_dot() {
+    #[test]
+    fn test_missing_node_and_edge_errors() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; }", &ctx).unwrap();
+
+        let err = graph
+            .set_attr_on_node("Z", NodeAttr::Width(1.0))
+            .unwrap_err();
+        assert!(matches!(err, Error::NodeNotFound(name) if name == "Z"));
+
+        let err = graph
+            .set_attr_on_edge("Z->A", EdgeAttr::Weight(1.0))
+            .unwrap_err();
+        assert!(matches!(err, Error::EdgeNotFound(name) if name == "Z->A"));
+    }
+
+    #[test]
+    fn test_layout_warnings_are_captured() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A [shape=nonexistent]; }", &ctx).unwrap();
+
+        graph.set_layout(Layout::Dot).unwrap();
+
+        let warnings = ctx.warnings();
+        assert!(warnings.iter().any(|w| w.message.contains("nonexistent")));
+    }
+
+    #[test]
+    fn test_graph_parse_invalid_dot() {
+        let ctx = dummy_context();
+        let dot = "digraph G {\n A -> ;\n}";
+
+        let err = Graph::parse(dot, &ctx).err().expect("invalid DOT should not parse");
+        match err {
+            Error::InvalidDot(diagnostics) => {
+                assert!(diagnostics.iter().any(|d| d.severity == Severity::Error));
+                assert!(diagnostics.iter().any(|d| d.line == Some(2)));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_layout_info() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B [label=\"go\"]; }", &ctx).unwrap();
+        assert!(matches!(graph.layout_info(), Err(Error::NotLaidOut)));
+
+        graph.set_layout(Layout::Dot).unwrap();
+        let info = graph.layout_info().unwrap();
+        assert_eq!(info.nodes.len(), 2);
+        assert!(info.bounding_box.width() > 0.0);
+
+        let a = info.node("A").unwrap();
+        let b = info.node("B").unwrap();
+        assert!(info.bounding_box.contains(a.position));
+        // default node size is 0.75in x 0.5in
+        assert_eq!(a.width, 54.0);
+        assert_eq!(a.height, 36.0);
+        // rankdir=TB puts the tail above the head
+        assert!(a.position.y > b.position.y);
+        assert_eq!(a.label.as_ref().unwrap().text, "A");
+
+        let edge = info.edge("A->B").unwrap();
+        assert_eq!((edge.tail.as_str(), edge.head.as_str()), ("A", "B"));
+        let spline = &edge.splines[0];
+        assert_eq!(spline.points.len() % 3, 1);
+        assert!(spline.end_arrow.is_some());
+        assert_eq!(edge.label.as_ref().unwrap().text, "go");
+    }
+
+    #[test]
+    fn test_render_to_writer_and_file() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; }", &ctx).unwrap();
+        graph.set_layout(Layout::Dot).unwrap();
+
+        let expected = ctx.render(&graph, OutputFormat::Svg).unwrap();
+        let mut streamed = Vec::new();
+        ctx.render_to_writer(&graph, OutputFormat::Svg, &mut streamed).unwrap();
+        assert_eq!(streamed, expected);
+
+        let path = std::env::temp_dir().join(format!("wrappedviz-{}.svg", std::process::id()));
+        ctx.render_to_file(&graph, OutputFormat::Svg, &path).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_render_many() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; }", &ctx).unwrap();
+        let formats = [OutputFormat::Svg, OutputFormat::Cmapx, OutputFormat::Plain];
+
+        // a graph without a layout is laid out on a copy and stays as it is
+        let copied = ctx.render_many(&graph, &formats);
+        assert_eq!(copied.len(), 3);
+        assert!(copied.values().all(|output| output.is_ok()));
+        assert!(!graph.is_laid_out());
+
+        graph.layout(Layout::Dot).unwrap();
+        let outputs = ctx.render_many(&graph, &formats);
+        for format in formats {
+            let output = outputs[&format].as_ref().unwrap();
+            assert_eq!(output, &ctx.render(&graph, format).unwrap());
+        }
+        // the copy got the same layout
+        assert_eq!(outputs[&OutputFormat::Plain].as_ref().unwrap(), copied[&OutputFormat::Plain].as_ref().unwrap());
+    }
+
+    #[test]
+    fn test_render_to_writer_reports_write_errors() {
+        struct FailingWriter;
+        impl Write for FailingWriter {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk full"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; }", &ctx).unwrap();
+        graph.set_layout(Layout::Dot).unwrap();
+
+        let err = ctx.render_to_writer(&graph, OutputFormat::Svg, FailingWriter).unwrap_err();
+        assert!(matches!(err, Error::Io(e) if e.to_string() == "disk full"));
+    }
+
+    #[test]
+    fn test_clusters_are_freed_with_the_root_graph() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; }", &ctx).unwrap();
+        {
+            let mut cluster = graph.add_cluster("outer").unwrap();
+            assert_eq!(cluster.name(), "cluster_outer");
+            cluster.add_node("A").unwrap();
+            cluster.add_node("C").unwrap();
+            cluster.set_attr(CommonAttr::Label("outer".into())).unwrap();
+            let mut inner = cluster.add_cluster("inner").unwrap();
+            inner.add_node("D").unwrap();
+            // dropping the handles must not close the subgraphs
+        }
+        // asking again returns the existing cluster
+        assert_eq!(graph.add_cluster("outer").unwrap().name(), "cluster_outer");
+        assert!(graph.nodes.contains_key("C"));
+        assert!(graph.nodes.contains_key("D"));
+
+        graph.set_layout(Layout::Dot).unwrap();
+        let dot = graph.to_dot().unwrap();
+        assert!(dot.contains("cluster_outer"));
+        assert!(dot.contains("cluster_inner"));
+    }
+
+    #[test]
+    fn test_relayout_and_drop_order() {
+        let ctx = dummy_context();
+        let mut first = Graph::parse("digraph G { A -> B; }", &ctx).unwrap();
+        let mut second = Graph::parse("digraph H { C -> D; }", &ctx).unwrap();
+        second.add_cluster("c").unwrap().add_node("C").unwrap();
+
+        for layout in [Layout::Dot, Layout::Neato, Layout::Dot] {
+            first.set_layout(layout).unwrap();
+            second.set_layout(layout).unwrap();
+            ctx.render(&first, OutputFormat::Svg).unwrap();
+            ctx.render(&second, OutputFormat::Svg).unwrap();
+        }
+
+        // graphs are independent of each other, any drop order is fine
+        drop(first);
+        assert!(ctx.render(&second, OutputFormat::Svg).is_ok());
+        drop(second);
+
+        // a graph that never got a layout is closed without freeing one
+        let unlaid = Graph::parse("digraph G { A; }", &ctx).unwrap();
+        drop(unlaid);
+    }
+
+    /// repeats the parse, layout, edit and free cycles, run under valgrind by `valgrind.sh`
+    /// so a leak or a use after free shows up as a failure instead of going unnoticed
+    #[test]
+    fn test_memory_cycles() {
+        for _ in 0..5 {
+            let ctx = dummy_context();
+            let mut graph = Graph::parse(
+                "digraph G { subgraph cluster_a { A; subgraph cluster_b { B; } } A -> B; B -> C; }",
+                &ctx,
+            )
+            .unwrap();
+            graph.layout(Layout::Dot).unwrap();
+            ctx.render(&graph, OutputFormat::Svg).unwrap();
+
+            graph.remove_cluster("b").unwrap();
+            graph.add_cluster("b").unwrap().add_node("C").unwrap();
+            graph.remove_edge("A->B").unwrap();
+            graph.relayout().unwrap();
+            graph.layout_info().unwrap();
+
+            graph.remove_node("B").unwrap();
+            graph.remove_cluster("a").unwrap();
+            graph.layout(Layout::Neato).unwrap();
+            ctx.render(&graph, OutputFormat::Svg).unwrap();
+
+            // a graph that is still laid out when it is dropped, and one that never was
+            drop(graph);
+            drop(Graph::parse("digraph G { A; }", &ctx).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_layout_lifecycle() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; }", &ctx).unwrap();
+        assert!(matches!(ctx.render(&graph, OutputFormat::Svg), Err(Error::NotLaidOut)));
+        assert!(matches!(graph.relayout(), Err(Error::NotLaidOut)));
+
+        graph.layout(Layout::Dot).unwrap();
+        assert!(graph.is_laid_out() && !graph.is_layout_stale());
+        ctx.render(&graph, OutputFormat::Svg).unwrap();
+
+        graph.add_node("C");
+        assert!(graph.is_layout_stale());
+        assert!(matches!(ctx.render(&graph, OutputFormat::Svg), Err(Error::StaleLayout)));
+        assert!(matches!(graph.layout_info(), Err(Error::StaleLayout)));
+
+        graph.relayout().unwrap();
+        assert_eq!(graph.layout_engine(), Some(Layout::Dot));
+        assert_eq!(graph.layout_info().unwrap().nodes.len(), 3);
+
+        // switching engines replaces the layout
+        graph.layout(Layout::Circo).unwrap();
+        ctx.render(&graph, OutputFormat::Svg).unwrap();
+
+        graph.clear_layout();
+        assert!(!graph.is_laid_out());
+        assert!(matches!(ctx.render(&graph, OutputFormat::Svg), Err(Error::NotLaidOut)));
+        graph.relayout().unwrap();
+        assert_eq!(graph.layout_engine(), Some(Layout::Circo));
+
+        graph.set_attr_on_graph(crate::style::GraphAttr::RankDir(crate::style::RankDir::LR)).unwrap();
+        assert!(graph.is_layout_stale());
+    }
+
+    #[test]
+    fn test_graph_kinds() {
+        let ctx = dummy_context();
+        assert_eq!(Graph::empty(&ctx).kind(), GraphKind::Directed);
+
+        let parsed = Graph::parse("strict graph S { a -- b; b -- a; }", &ctx).unwrap();
+        assert_eq!(parsed.kind(), GraphKind::StrictUndirected);
+        assert_eq!(parsed.edges.len(), 1);
+        assert!(parsed.edges.contains_key("a--b"));
+
+        for kind in [
+            GraphKind::Directed,
+            GraphKind::StrictDirected,
+            GraphKind::Undirected,
+            GraphKind::StrictUndirected,
+        ] {
+            let mut graph = Graph::with_kind("network", kind, &ctx).unwrap();
+            assert_eq!(graph.kind(), kind);
+            assert_eq!(graph.name(), "network");
+            graph.add_node("a");
+            graph.add_node("b");
+            graph.add_edge("a", "b", "ab").unwrap();
+            graph.layout(Layout::Dot).unwrap();
+            let dot = graph.to_dot().unwrap();
+            assert!(dot.starts_with(&format!("{} network {{", kind.keyword())), "{}", dot);
+            assert!(dot.contains(&format!("a {} b", kind.edge_op())), "{}", dot);
+        }
+
+        assert!(matches!(
+            Graph::with_kind("a\0b", GraphKind::Directed, &ctx),
+            Err(Error::InteriorNul(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_nodes_edges_and_clusters() {
         let ctx = dummy_context();
-        let dot = "invalid_dot {";
+        let mut graph = Graph::parse("digraph G { A -> B; B -> C; C -> A; D; }", &ctx).unwrap();
+        graph.add_cluster("group").unwrap().add_node("D").unwrap();
+        graph.layout(Layout::Dot).unwrap();
 
-        // Graphviz will likely still create a graph but with no nodes/edges
-        let graph = Graph::new(dot, &ctx);
-        assert!(graph.nodes.is_empty() || graph.nodes.len() == 1);
-        // There shouldn’t be edges if dot is invalid
+        graph.remove_node("B").unwrap();
+        assert!(!graph.nodes.contains_key("B"));
+        assert_eq!(graph.edges.keys().collect::<Vec<_>>(), vec!["C->A"]);
+        assert!(matches!(ctx.render(&graph, OutputFormat::Svg), Err(Error::StaleLayout)));
+
+        graph.remove_edge("C->A").unwrap();
         assert!(graph.edges.is_empty());
-    }*/
+        assert!(graph.nodes.contains_key("C"));
+
+        graph.remove_cluster("group").unwrap();
+        assert!(graph.nodes.contains_key("D"));
+        assert!(matches!(graph.remove_cluster("group"), Err(Error::ClusterNotFound(_))));
+        assert!(matches!(graph.remove_node("B"), Err(Error::NodeNotFound(_))));
+        assert!(matches!(graph.remove_edge("A->B"), Err(Error::EdgeNotFound(_))));
+
+        graph.relayout().unwrap();
+        let dot = graph.to_dot().unwrap();
+        assert!(!dot.contains("cluster_group"));
+        assert!(!dot.contains("B"));
+        assert_eq!(graph.layout_info().unwrap().nodes.len(), 3);
+    }
+
+    #[test]
+    fn test_read_attributes() {
+        let ctx = dummy_context();
+        let mut graph =
+            Graph::parse("digraph G { rankdir=LR; A [shape=box, color=red]; A -> B [weight=2]; }", &ctx).unwrap();
+
+        assert_eq!(graph.get_attr_on_node("A", "shape").unwrap().as_deref(), Some("box"));
+        assert_eq!(graph.get_attr_on_node("B", "shape").unwrap(), None);
+        assert_eq!(graph.get_attr_on_node("A", "undeclared").unwrap(), None);
+        assert_eq!(graph.get_attr_on_edge("A->B", "weight").unwrap().as_deref(), Some("2"));
+        assert_eq!(graph.get_attr_on_graph("rankdir").unwrap().as_deref(), Some("LR"));
+        assert!(matches!(graph.get_attr_on_node("Z", "shape"), Err(Error::NodeNotFound(_))));
+
+        assert_eq!(
+            graph.get_node_attribute("A", "shape").unwrap(),
+            Some(NodeAttribute::try_from(("shape", "box")).unwrap())
+        );
+        assert!(graph.get_edge_attribute("A->B", "weight").unwrap().is_some());
+        assert!(graph.get_graph_attribute("rankdir").unwrap().is_some());
+
+        let attrs: HashMap<String, String> = graph.node_attrs("A").unwrap().collect();
+        assert_eq!(attrs["shape"], "box");
+        assert_eq!(attrs["color"], "red");
+        assert_eq!(graph.node_attrs("B").unwrap().count(), 0);
+        assert!(graph.graph_attrs().any(|(name, value)| name == "rankdir" && value == "LR"));
+
+        // computed values can be read back after a layout, without being written into the graph
+        graph.layout(Layout::Dot).unwrap();
+        assert!(graph.get_attr_on_node("A", "pos").unwrap().is_some());
+        assert!(graph.get_attr_on_graph("bb").unwrap().is_some());
+        assert!(graph.get_attr_on_edge("A->B", "pos").unwrap().unwrap().starts_with("e,"));
+        assert!(!graph.edge_attrs("A->B").unwrap().any(|(name, _)| name == "pos"));
+        assert!(!graph.node_attrs("A").unwrap().any(|(name, _)| name == "width"));
+    }
+
+    #[test]
+    fn test_relayout_uses_new_sizes() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A [label=\"a rather long label\"]; A -> B; }", &ctx).unwrap();
+        graph.layout(Layout::Dot).unwrap();
+        let wide: f64 = graph.get_attr_on_node("A", "width").unwrap().unwrap().parse().unwrap();
+
+        graph.set_attr_on_node("A", NodeAttribute::Common(CommonAttr::Label("a".into()))).unwrap();
+        graph.relayout().unwrap();
+        let narrow: f64 = graph.get_attr_on_node("A", "width").unwrap().unwrap().parse().unwrap();
+        assert!(narrow < wide);
+        let width = graph.layout_info().unwrap().node("A").unwrap().width;
+        assert!((narrow * 72.0 - width).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_traversal() {
+        let ctx = dummy_context();
+        let graph = Graph::parse("digraph G { A -> B; A -> C; C -> A; B -> B; D; }", &ctx).unwrap();
+
+        let names: Vec<String> = graph.nodes().map(|n| n.name().to_string()).collect();
+        assert_eq!(names, vec!["A", "B", "C", "D"]);
+        assert_eq!(graph.edges().count(), 4);
+
+        let successors: Vec<String> = graph.successors("A").unwrap().map(|n| n.name().to_string()).collect();
+        assert_eq!(successors, vec!["B", "C"]);
+        let predecessors: Vec<String> = graph.predecessors("A").unwrap().map(|n| n.name().to_string()).collect();
+        assert_eq!(predecessors, vec!["C"]);
+
+        let mut neighbors: Vec<String> = graph.neighbors("A").unwrap().map(|n| n.name().to_string()).collect();
+        neighbors.sort();
+        assert_eq!(neighbors, vec!["B", "C", "C"]);
+
+        let edge = graph.in_edges("B").unwrap().find(|e| e.tail().name() == "A").unwrap();
+        assert_eq!(edge.head().name(), "B");
+        assert_eq!(edge.name(), None);
+
+        assert_eq!(graph.out_degree("A").unwrap(), 2);
+        assert_eq!(graph.in_degree("A").unwrap(), 1);
+        assert_eq!(graph.degree("A").unwrap(), 3);
+        assert_eq!(graph.degree("B").unwrap(), 3);
+        assert_eq!(graph.degree("D").unwrap(), 0);
+        assert_eq!(graph.incident_edges("D").unwrap().count(), 0);
+        assert!(matches!(graph.successors("Z"), Err(Error::NodeNotFound(_))));
+    }
+
+    #[test]
+    fn test_parallel_and_named_edges() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse(
+            "digraph G { A -> B; A -> B; A -> B [key=express]; B -> A; }",
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(graph.edges.len(), 4);
+        assert_eq!(graph.edges_between("A", "B").unwrap(), vec!["A->B", "A->B#2", "express"]);
+        assert_eq!(graph.edges_between("B", "A").unwrap(), vec!["B->A"]);
+        assert!(matches!(graph.edges_between("A", "Z"), Err(Error::NodeNotFound(_))));
+
+        graph
+            .set_attr_on_edge("A->B#2", EdgeAttr::Color("#ff0000".parse().unwrap()))
+            .unwrap();
+        assert_eq!(graph.get_attr_on_edge("A->B#2", "color").unwrap().as_deref(), Some("#ff0000"));
+        assert_eq!(graph.get_attr_on_edge("A->B", "color").unwrap(), None);
+
+        let named = graph.edges().find(|e| e.name() == Some("express")).unwrap();
+        assert_eq!(graph.edge_key(&named), Some("express"));
+
+        let undirected = Graph::parse("graph G { a -- b; b -- a; }", &ctx).unwrap();
+        assert_eq!(undirected.edges_between("a", "b").unwrap(), vec!["a--b", "b--a"]);
+    }
+
+    #[test]
+    fn test_default_attrs() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { node [color=blue]; A -> B; C [color=green]; }", &ctx).unwrap();
+
+        graph.set_default_node_attr(NodeAttr::Shape(NodeShape::Box)).unwrap();
+        graph.set_default_node_attr(NodeAttr::Color("#ff0000".parse().unwrap())).unwrap();
+        graph.set_default_edge_attr(EdgeAttr::Weight(2.0)).unwrap();
+        graph.set_default_graph_attr(GraphAttr::RankDir(RankDir::LR)).unwrap();
+
+        assert_eq!(graph.get_attr_on_node("A", "shape").unwrap().as_deref(), Some("box"));
+        assert_eq!(graph.get_attr_on_node("A", "color").unwrap().as_deref(), Some("#ff0000"));
+        assert_eq!(graph.get_attr_on_node("C", "color").unwrap().as_deref(), Some("green"));
+        assert_eq!(graph.get_attr_on_edge("A->B", "weight").unwrap().as_deref(), Some("2"));
+        assert_eq!(graph.get_attr_on_graph("rankdir").unwrap().as_deref(), Some("LR"));
+
+        graph.add_node("D");
+        assert_eq!(graph.get_attr_on_node("D", "shape").unwrap().as_deref(), Some("box"));
+    }
+
+    #[test]
+    fn test_apply_theme() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; B [shape=circle]; }", &ctx).unwrap();
+        let theme = GraphTheme {
+            name: "boxes".into(),
+            layout: Layout::Neato,
+            node_attrs: vec![NodeAttr::Shape(NodeShape::Box)],
+            edge_attrs: vec![EdgeAttr::Color("#ff0000".parse().unwrap())],
+        };
+        graph.apply_theme(&theme).unwrap();
+
+        assert_eq!(graph.get_attr_on_node("A", "shape").unwrap().as_deref(), Some("box"));
+        assert_eq!(graph.get_attr_on_node("B", "shape").unwrap().as_deref(), Some("circle"));
+        assert_eq!(graph.get_attr_on_edge("A->B", "color").unwrap().as_deref(), Some("#ff0000"));
+        assert_eq!(graph.get_attr_on_graph("layout").unwrap().as_deref(), Some("neato"));
+        // nothing ran yet, the theme only configures the engine
+        assert_eq!(graph.layout_engine(), None);
+        assert_eq!(graph.configured_layout(), Some(Layout::Neato));
+        graph.relayout().unwrap();
+        assert_eq!(graph.layout_engine(), Some(Layout::Neato));
+    }
+
+    #[test]
+    fn test_remove_and_recreate_cluster() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A; B; }", &ctx).unwrap();
+        for _ in 0..3 {
+            let mut cluster = graph.add_cluster("group").unwrap();
+            assert_eq!(cluster.nodes().count(), 0);
+            cluster.add_node("A").unwrap();
+            cluster.set_cluster_attr(ClusterAttr::Style(ClusterStyle::Filled)).unwrap();
+            graph.remove_cluster("group").unwrap();
+        }
+        // the recreated cluster is a fresh subgraph, not the removed one with its members
+        let cluster = graph.add_cluster("group").unwrap();
+        assert_eq!(cluster.nodes().count(), 0);
+        assert_eq!(graph.clusters().len(), 1);
+    }
+
+    #[test]
+    fn test_parsed_nested_clusters() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse(
+            "digraph G { subgraph cluster_backend { label=Backend; subgraph cluster_db { pg; } api -> pg; } \
+             subgraph ranks { rank=same; web; } web -> api; }",
+            &ctx,
+        )
+        .unwrap();
+
+        let names: Vec<String> = graph.subgraphs().map(|s| s.name()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"ranks".to_string()));
+        let clusters: Vec<String> = graph.clusters().iter().map(|c| c.name()).collect();
+        assert_eq!(clusters, vec!["cluster_backend", "cluster_db"]);
+
+        let backend = graph.clusters()[0];
+        assert!(backend.contains_node("pg"));
+        assert!(!backend.contains_node("web"));
+        assert_eq!(backend.edges().count(), 1);
+        assert_eq!(
+            backend.get_cluster_attribute("label").unwrap(),
+            Some(ClusterAttribute::Common(CommonAttr::Label("Backend".into())))
+        );
+
+        let mut db = graph.cluster("db").unwrap();
+        db.add_node("redis").unwrap();
+        db.set_cluster_attr(ClusterAttr::Style(ClusterStyle::Filled)).unwrap();
+        assert_eq!(db.get_attr("style").unwrap().as_deref(), Some("filled"));
+        assert_eq!(db.nodes().count(), 2);
+
+        graph.remove_cluster("db").unwrap();
+        assert!(matches!(graph.cluster("db"), Err(Error::ClusterNotFound(_))));
+        assert_eq!(graph.clusters().len(), 1);
+        assert!(graph.nodes.contains_key("redis"));
+    }
+
+    #[test]
+    fn test_cluster_membership() {
+        let ctx = dummy_context();
+        let mut graph = Graph::empty(&ctx);
+        graph.add_node("A");
+        graph.add_node("B");
+        graph.add_node("C");
+        graph.add_edge("A", "B", "ab").unwrap();
+
+        let mut outer = graph.add_cluster("outer").unwrap();
+        outer.include_edge("ab").unwrap();
+        let mut inner = outer.add_cluster("inner").unwrap();
+        assert_eq!(inner.add_edge("B", "C", "bc").unwrap(), "bc");
+        assert!(matches!(inner.add_edge("B", "Z", "bz"), Err(Error::NodeNotFound(_))));
+        assert!(matches!(outer.include_edge("nope"), Err(Error::EdgeNotFound(_))));
+
+        assert!(outer.contains_node("A") && outer.contains_node("C"));
+        assert_eq!(outer.edges().count(), 2);
+        assert_eq!(outer.subgraphs().count(), 1);
+        assert!(graph.edges.contains_key("bc"));
+        assert_eq!(graph.cluster("inner").unwrap().nodes().count(), 2);
+    }
+
+    #[test]
+    fn test_html_label() {
+        use crate::style::html::{Cell, Row, Table};
+
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; }", &ctx).unwrap();
+        let label = Table::new().row(Row::new().cell(Cell::new("in").port("in")).cell(Cell::new("a < b")));
+        graph.set_attr_on_node("A", CommonAttr::HtmlLabel(label.into())).unwrap();
+        graph.set_attr_on_node("B", CommonAttr::Label("<plain>".into())).unwrap();
+
+        let raw_a = graph.nodes["A"].raw;
+        let raw_b = graph.nodes["B"].raw;
+        unsafe {
+            let name = CString::new("label").unwrap();
+            assert_ne!(aghtmlstr(agget(raw_a as *mut _, name.as_ptr() as *mut c_char)), 0);
+            assert_eq!(aghtmlstr(agget(raw_b as *mut _, name.as_ptr() as *mut c_char)), 0);
+        }
+        let markup = graph.get_attr_on_node("A", "label").unwrap().unwrap();
+        assert!(markup.contains("<TD PORT=\"in\">in</TD><TD>a &lt; b</TD>"));
+    }
+
+    #[test]
+    fn test_edge_ports() {
+        use crate::style::html::{Cell, Row, Table};
+        use crate::style::record::RecordLabel;
+        use crate::style::{CompassPoint, Port};
+
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; B -> C; }", &ctx).unwrap();
+        graph.set_attr_on_node("A", NodeAttr::Shape(NodeShape::Record)).unwrap();
+        let record = RecordLabel::new().port_field("in", "in").nested(RecordLabel::new().port_field("out", "out"));
+        graph.set_attr_on_node("A", CommonAttr::from(record)).unwrap();
+        let table = Table::new().row(Row::new().cell(Cell::new("x").port("x")));
+        graph.set_attr_on_node("B", CommonAttr::HtmlLabel(table.into())).unwrap();
+
+        graph
+            .set_attr_on_edge("A->B", EdgeAttr::TailPort(Port::named("out").at(CompassPoint::S)))
+            .unwrap();
+        graph.set_attr_on_edge("A->B", EdgeAttr::HeadPort(Port::named("x"))).unwrap();
+        graph.set_attr_on_edge("B->C", EdgeAttr::HeadPort(Port::compass(CompassPoint::N))).unwrap();
+        assert_eq!(graph.get_attr_on_edge("A->B", "tailport").unwrap().as_deref(), Some("out:s"));
+
+        let err = graph.set_attr_on_edge("A->B", EdgeAttr::HeadPort(Port::named("missing")));
+        assert!(matches!(err, Err(Error::PortNotFound { node, port }) if node == "B" && port == "missing"));
+        assert!(graph.set_attr_on_edge("B->C", EdgeAttr::HeadPort(Port::named("x"))).is_err());
+    }
+
+    #[test]
+    fn test_rust_graph_round_trip() {
+        let ctx = dummy_context();
+        let dot = r##"digraph G {
+            rankdir=LR; label="Title";
+            node [shape=box];
+            A [label="Start"]; B; C [label=<<B>c</B>>];
+            A -> B [color="#ff0000"]; B -> C; C -> C;
+            subgraph cluster_outer {
+                style=filled;
+                A; B;
+                subgraph cluster_inner { label="Inner"; node [shape=circle]; C; }
+            }
+        }"##;
+        let parsed = Graph::parse(dot, &ctx).unwrap();
+        let rust = parsed.to_rust_graph();
+
+        assert_eq!(rust.name(), "G");
+        assert!(rust.attributes().contains(&GraphAttr::RankDir(RankDir::LR)));
+        assert!(rust.cluster_attributes().contains(&CommonAttr::Label("Title".into()).into()));
+        assert_eq!(rust.node_defaults(), &[NodeAttr::Shape(NodeShape::Box)]);
+        assert_eq!(rust.node("A").unwrap().label(), "Start");
+        assert_eq!(rust.node("B").unwrap().label(), "B");
+        assert!(rust.node("C").unwrap().attributes().iter().any(|attr| attr.is_html()));
+        assert_eq!(rust.edge("C->C").unwrap().dest(), "C");
+        assert_eq!(rust.edges().count(), 3);
+
+        let (name, outer) = rust.clusters().next().unwrap();
+        assert_eq!(name, "cluster_outer");
+        assert!(outer.cluster_attributes().contains(&ClusterAttr::Style(ClusterStyle::Filled).into()));
+        assert_eq!(outer.nodes().count(), 3);
+        let (_, inner) = outer.clusters().next().unwrap();
+        assert_eq!(inner.node_defaults(), &[NodeAttr::Shape(NodeShape::Circle)]);
+        assert_eq!(inner.edges().count(), 1);
+
+        let rebuilt = Graph::from_rust_graph(&rust, &ctx).unwrap();
+        assert_eq!(rebuilt.nodes.len(), 3);
+        assert_eq!(rebuilt.get_attr_on_node("A", "label").unwrap().as_deref(), Some("Start"));
+        assert_eq!(rebuilt.get_attr_on_node("B", "shape").unwrap().as_deref(), Some("box"));
+        // C was declared in the root before the cluster, so `node [shape=circle]` doesn't reach it
+        assert_eq!(parsed.get_attr_on_node("C", "shape").unwrap().as_deref(), Some("box"));
+        assert_eq!(rebuilt.get_attr_on_node("C", "shape").unwrap().as_deref(), Some("box"));
+        assert_eq!(rebuilt.get_attr_on_edge("A->B", "color").unwrap().as_deref(), Some("#ff0000"));
+        let clusters = rebuilt.clusters();
+        assert_eq!(clusters.len(), 2);
+        assert!(clusters[1].contains_node("C"));
+        assert_eq!(clusters[1].get_attr("label").unwrap().as_deref(), Some("Inner"));
+        assert_eq!(rebuilt.to_rust_graph(), rust);
+    }
+
+    #[test]
+    fn test_to_rust_graph_keeps_unstored_edges() {
+        let ctx = dummy_context();
+        let mut graph = Graph::parse("digraph G { A -> B; A -> B; B -> C; }", &ctx).unwrap();
+        // an edge graphviz holds without a key in the map, like one created behind our back
+        graph.edges.remove("A->B#2").unwrap();
+
+        let rust = graph.to_rust_graph();
+        assert_eq!(rust.edges().count(), 3);
+        assert_eq!(rust.edge("A->B#2").unwrap().dest(), "B");
+        assert_eq!(graph.to_rust_graph(), rust);
+    }
+
+    #[test]
+    fn test_cluster_only_defaults() {
+        let ctx = dummy_context();
+        let nodes = ["A", "B"].map(|id| rgraph::Node::new(id, id));
+        let mut rust = RustGraph::from_parts("G".into(), nodes.to_vec(), vec![]);
+        let mut cluster = RustGraph::from_parts("c".into(), vec![nodes[1].clone()], vec![]);
+        cluster.set_default_node_attr(NodeAttr::Color("#ff0000".parse().unwrap()));
+        rust.add_cluster(cluster).unwrap();
+
+        let mut graph = Graph::from_rust_graph(&rust, &ctx).unwrap();
+        // `color` is declared at the root with an empty default, the cluster's stays local
+        assert_eq!(graph.get_attr_on_node("A", "color").unwrap(), None);
+        assert_eq!(graph.get_attr_on_node("B", "color").unwrap(), None);
+        graph.add_node("C");
+        assert_eq!(graph.get_attr_on_node("C", "color").unwrap(), None);
+        graph.cluster("c").unwrap().add_node("D").unwrap();
+        assert_eq!(graph.get_attr_on_node("D", "color").unwrap().as_deref(), Some("#ff0000"));
+
+        let rebuilt = graph.to_rust_graph();
+        assert!(rebuilt.node_defaults().is_empty());
+        let (_, cluster) = rebuilt.clusters().next().unwrap();
+        assert_eq!(cluster.node_defaults(), &[NodeAttr::Color("#ff0000".parse().unwrap())]);
+    }
+
+    #[test]
+    fn test_graph_parse_all() {
+        let ctx = dummy_context();
+        let dot = "digraph A { a -> b; }\ndigraph B { c; }";
+
+        let graphs = Graph::parse_all(dot, &ctx).unwrap();
+        assert_eq!(graphs.len(), 2);
+        assert_eq!(graphs[0].edges.len(), 1);
+        assert!(graphs[1].nodes.contains_key("c"));
+
+        assert!(Graph::parse_all("", &ctx).unwrap().is_empty());
+        assert!(matches!(
+            Graph::parse_all("digraph A { a; } digraph B {", &ctx),
+            Err(Error::InvalidDot(_))
+        ));
+    }
+
+    #[test]
+    fn test_plugins() {
+        let ctx = dummy_context();
+        ctx.check_plugins().unwrap();
+        assert!(ctx.plugins(PluginKind::Layout).iter().any(|p| p == "dot"));
+        assert!(ctx.plugins(PluginKind::Device).iter().any(|p| p == "svg"));
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let ctx = dummy_context();
+        assert!(ctx.version().chars().next().is_some_and(|c| c.is_ascii_digit()));
+        assert!(ctx.available_layouts().contains(&Layout::Dot));
+        let formats = ctx.available_formats();
+        assert!(formats.contains(&OutputFormat::Svg));
+        assert!(formats.contains(&OutputFormat::Dot));
+
+        let mut graph = Graph::parse("digraph { a -> b; }", &ctx).unwrap();
+        graph.layout(Layout::Dot).unwrap();
+        // kitty needs a plugin that is rarely installed, skip where it is
+        if !formats.contains(&OutputFormat::Kitty) {
+            match ctx.render(&graph, OutputFormat::Kitty) {
+                Err(Error::UnsupportedFormat { format, available }) => {
+                    assert_eq!(format, OutputFormat::Kitty);
+                    assert_eq!(available, formats);
+                }
+                other => panic!("expected UnsupportedFormat, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    #[cfg(feature = "builtins")]
+    #[test]
+    fn test_with_builtins() {
+        let ctx = Context::with_builtins();
+        ctx.check_plugins().unwrap();
+        let layouts = ctx.plugins(PluginKind::Layout);
+        assert!(layouts.iter().any(|p| p == "dot"));
+        assert!(layouts.iter().any(|p| p == "neato"));
+
+        let mut graph = Graph::parse("digraph { a -> b; }", &ctx).unwrap();
+        graph.layout(Layout::Neato).unwrap();
+        assert!(ctx.render(&graph, OutputFormat::Svg).is_ok());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,276 @@
+//! The crate wide error type.
+//!
+//! Every fallible operation in `cgraph`, `rgraph` and the attribute parsers in `style`
+//! returns [`Error`], so callers can match on the kind of failure instead of comparing strings.
+
+use crate::{OutputFormat, PluginKind};
+use std::ffi::NulError;
+use std::fmt;
+use std::string::FromUtf8Error;
+
+#[derive(Debug)]
+pub enum Error {
+    /// no node with the given name exists in the graph
+    NodeNotFound(String),
+    /// no edge with the given name exists in the graph
+    EdgeNotFound(String),
+    /// a string passed to graphviz contained an interior NUL byte
+    InteriorNul(NulError),
+    /// graphviz returned a null graph pointer
+    NullGraph,
+    /// `gvLayout` returned a non-zero error code
+    LayoutFailed { code: i32, diagnostics: Vec<Diagnostic> },
+    /// `gvRenderData` failed for the given output format
+    RenderFailed { format: OutputFormat, diagnostics: Vec<Diagnostic> },
+    /// `gvRenderData` failed for a format registered with `Context::add_renderer`
+    CustomRenderFailed { format: String, diagnostics: Vec<Diagnostic> },
+    /// none of the installed graphviz plugins renders `format`, `available` lists those that do
+    UnsupportedFormat { format: OutputFormat, available: Vec<OutputFormat> },
+    /// a render plugin for the given format could not be registered
+    PluginFailed(String),
+    /// graphviz loaded no plugins of these kinds, usually because its plugin config is missing
+    PluginsMissing(Vec<PluginKind>),
+    /// graphviz refused to set an attribute on a graph, node or edge
+    AttributeFailed { name: String, value: String },
+    /// graphviz could not create the named cluster
+    ClusterFailed(String),
+    /// graphviz could not delete the named cluster
+    ClusterRemoveFailed(String),
+    /// no cluster with the given name exists in the graph
+    ClusterNotFound(String),
+    /// an edge port names a field that the node's record or HTML label doesn't define
+    PortNotFound { node: String, port: String },
+    /// rendered output was expected to be text but was not valid UTF-8
+    InvalidUtf8(FromUtf8Error),
+    /// a DOT fragment, attribute or enum variant could not be parsed
+    ParseFailed(String),
+    /// graphviz could not parse a DOT source, see the diagnostics for the offending line
+    InvalidDot(Vec<Diagnostic>),
+    /// layout results were requested before a layout engine ran on the graph
+    NotLaidOut,
+    /// the graph was edited after its last layout, call `relayout` first
+    StaleLayout,
+    /// a [`RenderPool`](crate::pool::RenderPool) queue is at its depth limit
+    QueueFull,
+    /// the [`RenderPool`](crate::pool::RenderPool) shut down before the job ran
+    PoolClosed,
+    /// a job did not finish within its timeout
+    Timeout,
+    /// an I/O operation backing a graphviz call failed
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match self {
+            NodeNotFound(name) => write!(f, "node '{}' not found", name),
+            EdgeNotFound(name) => write!(f, "edge '{}' not found", name),
+            InteriorNul(e) => write!(f, "string contains an interior nul byte: {}", e),
+            NullGraph => write!(f, "graphviz returned a null graph"),
+            LayoutFailed { code, diagnostics } => {
+                write!(f, "gvLayout failed with error code {}", code)?;
+                write_diagnostics(f, diagnostics)
+            }
+            RenderFailed { format, diagnostics } => {
+                write!(f, "graphviz failed to render format '{}'", format)?;
+                write_diagnostics(f, diagnostics)
+            }
+            CustomRenderFailed { format, diagnostics } => {
+                write!(f, "graphviz failed to render custom format '{}'", format)?;
+                write_diagnostics(f, diagnostics)
+            }
+            UnsupportedFormat { format, available } => {
+                let available: Vec<String> = available.iter().map(|format| format.to_string()).collect();
+                write!(
+                    f,
+                    "format '{}' is not supported by the installed graphviz plugins, available: {}",
+                    format,
+                    available.join(", ")
+                )
+            }
+            PluginFailed(format) => write!(f, "failed to register a renderer for format '{}'", format),
+            PluginsMissing(kinds) => {
+                let kinds: Vec<String> = kinds.iter().map(|kind| kind.to_string()).collect();
+                write!(
+                    f,
+                    "graphviz loaded no {} plugins, its plugin config (config6) may be missing; \
+                     run `dot -c` or use `Context::with_builtins`",
+                    kinds.join(", ")
+                )
+            }
+            AttributeFailed { name, value } => {
+                write!(f, "failed to set attribute '{}'='{}'", name, value)
+            }
+            ClusterFailed(name) => write!(f, "failed to create cluster '{}'", name),
+            ClusterRemoveFailed(name) => write!(f, "failed to remove cluster '{}'", name),
+            ClusterNotFound(name) => write!(f, "cluster '{}' not found", name),
+            PortNotFound { node, port } => write!(f, "node '{}' has no port '{}'", node, port),
+            InvalidUtf8(e) => write!(f, "output is not valid utf-8: {}", e),
+            ParseFailed(msg) => write!(f, "parse error: {}", msg),
+            InvalidDot(diagnostics) => {
+                write!(f, "invalid DOT")?;
+                write_diagnostics(f, diagnostics)
+            }
+            NotLaidOut => write!(f, "graph has not been laid out, call `layout` first"),
+            StaleLayout => write!(f, "graph was edited after its last layout, call `relayout` first"),
+            QueueFull => write!(f, "render queue is full"),
+            PoolClosed => write!(f, "render pool is shut down"),
+            Timeout => write!(f, "render job timed out"),
+            Io(e) => write!(f, "i/o error: {}", e),
+        }
+    }
+}
+
+fn write_diagnostics(f: &mut fmt::Formatter<'_>, diagnostics: &[Diagnostic]) -> fmt::Result {
+    for diagnostic in diagnostics {
+        write!(f, "; {}", diagnostic)?;
+    }
+    Ok(())
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InteriorNul(e) => Some(e),
+            Error::InvalidUtf8(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<NulError> for Error {
+    fn from(e: NulError) -> Self {
+        Error::InteriorNul(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(e: FromUtf8Error) -> Self {
+        Error::InvalidUtf8(e)
+    }
+}
+
+/// How severe a message reported by graphviz is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A warning or error message reported by graphviz through its `agerr` machinery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// the message with the `Error: ` / `Warning: ` prefix removed
+    pub message: String,
+    /// the line of the DOT source the message refers to, for syntax errors
+    pub line: Option<u32>,
+}
+
+impl Diagnostic {
+    /// parses a single line as written by graphviz, e.g.
+    /// `Error: syntax error in line 3 near '}'`.
+    ///
+    /// lines without a prefix continue the previous message, so they take `previous` as severity.
+    pub fn parse(raw: &str, previous: Severity) -> Self {
+        let raw = raw.trim();
+        let (severity, message) = if let Some(rest) = raw.strip_prefix("Error:") {
+            (Severity::Error, rest.trim_start())
+        } else if let Some(rest) = raw.strip_prefix("Warning:") {
+            (Severity::Warning, rest.trim_start())
+        } else {
+            (previous, raw)
+        };
+        Self {
+            severity,
+            message: message.to_string(),
+            line: parse_line_number(message),
+        }
+    }
+}
+
+/// finds `line N` in a graphviz message
+fn parse_line_number(message: &str) -> Option<u32> {
+    let (_, rest) = message.split_once("line ")?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.message),
+            Severity::Error => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_display_messages() {
+        let err = Error::NodeNotFound("A".into());
+        assert_eq!(err.to_string(), "node 'A' not found");
+
+        let err = Error::LayoutFailed { code: -1, diagnostics: vec![] };
+        assert_eq!(err.to_string(), "gvLayout failed with error code -1");
+
+        let err = Error::RenderFailed {
+            format: OutputFormat::Svg,
+            diagnostics: vec![Diagnostic::parse("Error: renderer for svg is unavailable", Severity::Error)],
+        };
+        assert_eq!(
+            err.to_string(),
+            "graphviz failed to render format 'svg'; error: renderer for svg is unavailable"
+        );
+
+        let err = Error::InvalidDot(vec![Diagnostic::parse(
+            "Error: syntax error in line 2 near ';'",
+            Severity::Error,
+        )]);
+        assert_eq!(err.to_string(), "invalid DOT; error: syntax error in line 2 near ';'");
+
+        let err = Error::UnsupportedFormat {
+            format: OutputFormat::Kitty,
+            available: vec![OutputFormat::Svg, OutputFormat::Png],
+        };
+        assert_eq!(
+            err.to_string(),
+            "format 'kitty' is not supported by the installed graphviz plugins, available: svg, png"
+        );
+    }
+
+    #[test]
+    fn test_parse_diagnostic() {
+        let diag = Diagnostic::parse("Error: syntax error in line 3 near '}'", Severity::Warning);
+        assert_eq!(diag.severity, Severity::Error);
+        assert_eq!(diag.message, "syntax error in line 3 near '}'");
+        assert_eq!(diag.line, Some(3));
+
+        let diag = Diagnostic::parse("Warning: node A, port x unrecognized", Severity::Error);
+        assert_eq!(diag.severity, Severity::Warning);
+        assert_eq!(diag.line, None);
+
+        let diag = Diagnostic::parse("in label of node A", Severity::Error);
+        assert_eq!(diag.severity, Severity::Error);
+        assert_eq!(diag.message, "in label of node A");
+    }
+
+    #[test]
+    fn test_from_nul_error_keeps_source() {
+        let err: Error = CString::new("a\0b").unwrap_err().into();
+        assert!(matches!(err, Error::InteriorNul(_)));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
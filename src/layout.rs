@@ -0,0 +1,194 @@
+//! Typed results of a graphviz layout.
+//!
+//! These are plain data, filled in by [`crate::cgraph::Graph::layout_info`] after a layout engine ran.
+//! All coordinates and sizes are in points (1/72 inch) with the origin in the lower left corner,
+//! the same coordinate system graphviz uses in its `json` and `plain` output.
+
+/// A point in layout coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+/// An axis aligned box given by its lower left and upper right corners.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct BoundingBox {
+    pub lower_left: Point,
+    pub upper_right: Point,
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> f64 {
+        self.upper_right.x - self.lower_left.x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.upper_right.y - self.lower_left.y
+    }
+
+    /// returns `true` if `point` lies inside the box or on its border
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.lower_left.x
+            && point.x <= self.upper_right.x
+            && point.y >= self.lower_left.y
+            && point.y <= self.upper_right.y
+    }
+}
+
+/// A placed text label.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct LabelLayout {
+    pub text: String,
+    /// center of the label
+    pub position: Point,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Position and size of a single node.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct NodeLayout {
+    pub name: String,
+    /// center of the node
+    pub position: Point,
+    pub width: f64,
+    pub height: f64,
+    pub label: Option<LabelLayout>,
+    pub xlabel: Option<LabelLayout>,
+}
+
+impl NodeLayout {
+    /// the box the node's shape is drawn in
+    pub fn bounding_box(&self) -> BoundingBox {
+        let (half_w, half_h) = (self.width / 2.0, self.height / 2.0);
+        BoundingBox {
+            lower_left: Point::new(self.position.x - half_w, self.position.y - half_h),
+            upper_right: Point::new(self.position.x + half_w, self.position.y + half_h),
+        }
+    }
+}
+
+/// One piecewise cubic bezier curve of an edge.
+///
+/// `points` holds `3n + 1` control points: the start point followed by three points per segment.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct Spline {
+    pub points: Vec<Point>,
+    /// tip of the arrowhead at the tail end, the arrow is drawn from the first control point to here
+    pub start_arrow: Option<Point>,
+    /// tip of the arrowhead at the head end, the arrow is drawn from the last control point to here
+    pub end_arrow: Option<Point>,
+}
+
+/// The route and labels of a single edge.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct EdgeLayout {
+    /// the key the edge is stored under in the graph
+    pub name: String,
+    pub tail: String,
+    pub head: String,
+    /// usually a single spline, several for edges that are split by the layout (e.g. `concentrate`)
+    pub splines: Vec<Spline>,
+    pub label: Option<LabelLayout>,
+    pub head_label: Option<LabelLayout>,
+    pub tail_label: Option<LabelLayout>,
+    pub xlabel: Option<LabelLayout>,
+}
+
+/// Everything a layout engine computed for a graph.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct GraphLayout {
+    pub bounding_box: BoundingBox,
+    pub label: Option<LabelLayout>,
+    pub nodes: Vec<NodeLayout>,
+    pub edges: Vec<EdgeLayout>,
+}
+
+impl GraphLayout {
+    /// looks up the layout of a node by name
+    pub fn node(&self, name: &str) -> Option<&NodeLayout> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    /// looks up the layout of an edge by the key it is stored under in the graph
+    pub fn edge(&self, name: &str) -> Option<&EdgeLayout> {
+        self.edges.iter().find(|edge| edge.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounding_box_size_and_contains() {
+        let bb = BoundingBox {
+            lower_left: Point::new(0.0, 0.0),
+            upper_right: Point::new(62.0, 116.0),
+        };
+        assert_eq!(bb.width(), 62.0);
+        assert_eq!(bb.height(), 116.0);
+        assert!(bb.contains(Point::new(27.0, 18.0)));
+        assert!(!bb.contains(Point::new(-1.0, 18.0)));
+    }
+
+    #[test]
+    fn test_node_bounding_box_and_lookup() {
+        let node = NodeLayout {
+            name: "A".into(),
+            position: Point::new(27.0, 90.0),
+            width: 54.0,
+            height: 36.0,
+            label: None,
+            xlabel: None,
+        };
+        let bb = node.bounding_box();
+        assert_eq!(bb.lower_left, Point::new(0.0, 72.0));
+        assert_eq!(bb.upper_right, Point::new(54.0, 108.0));
+
+        let layout = GraphLayout {
+            bounding_box: bb,
+            label: None,
+            nodes: vec![node],
+            edges: vec![],
+        };
+        assert!(layout.node("A").is_some());
+        assert!(layout.node("B").is_none());
+        assert!(layout.edge("A->B").is_none());
+    }
+}
@@ -11,6 +11,7 @@
 //! - Output to formats like `svg`, `png`, `dot`, `json`, and many more
 //! - Extensible trait-based attribute system
 //! - Optional `serde` support for attribute enums
+//! - Optional `tracing` instrumentation, the library never prints to stdout
 //!
 //! ## Example
 //! This example will only work if bindgen is enabled (which is default)
@@ -24,7 +25,7 @@
 //!     let ctx = Context::new();
 //!
 //!     // Start with an empty directed graph
-//!     let mut graph = Graph::new("digraph G {}", &ctx);
+//!     let mut graph = Graph::parse("digraph G {}", &ctx).unwrap();
 //!
 //!     // Add nodes A and B
 //!     graph.add_node("A");
@@ -84,7 +85,7 @@
 //! </svg>
 //! 
 //! ## Safety
-//! While this crate provides a safe wrapper over unsafe C bindings, all `Graph` objects must be dropped before their parent `Context`.
+//! A `Graph` borrows the `Context` it was created with, so the compiler ensures every graph is dropped before its context. Clusters are returned as `Subgraph` handles that borrow their parent graph and never close the underlying graphviz subgraph themselves, graphviz frees them together with the root graph.
 //!
 //! ## Optional Features
 //! - `serde`: Enables serialization and deserialization for enums like `Layout`, `OutputFormat`, and attribute types.
@@ -104,7 +105,6 @@ use crate::style::NodeAttribute;
 use crate::style::EdgeAttribute;
 use crate::style::GraphAttr;
 use crate::style::ClusterAttribute;
-use std::error::Error;
 use crate::style::Attribute;
 use crate::style::{EdgeAttr, NodeAttr};
 use std::collections::HashMap;
@@ -114,8 +114,17 @@ use std::ptr;
 use std::str::FromStr;
 use std::ffi::CStr;
 pub use style::shape::NodeShape;
+pub use error::{Diagnostic, Error, Severity};
+/// the crate wide error type
+pub mod error;
 /// provides style attributes that can be passed to graphviz
 pub mod style;
+/// typed node positions, sizes and edge routes computed by a layout engine
+pub mod layout;
+/// parses the drawing operations of the xdot output formats
+pub mod xdot;
+/// logging macros, backed by `tracing` when the feature is enabled
+mod trace;
 /// provides serde compatible structures for easily defining graph themes.
 pub mod theme;
 /// raw FFI bindings generated by bindgen using libclang
@@ -128,6 +137,12 @@ pub mod rgraph;
 /// provides types that wrap graphviz
 #[cfg(all(feature = "bindings", not(target_arch = "wasm32")))]
 pub mod cgraph;
+/// custom output formats written in rust, registered as graphviz render plugins
+#[cfg(all(feature = "bindings", not(target_arch = "wasm32")))]
+pub mod render;
+/// a pool of worker threads rendering graphs for multi threaded programs
+#[cfg(all(feature = "bindings", not(target_arch = "wasm32")))]
+pub mod pool;
 
 use crate::style::{CommonAttr};
 
@@ -170,7 +185,7 @@ impl fmt::Display for Layout {
 }
 
 impl FromStr for Layout {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         match input.to_ascii_lowercase().as_str() {
@@ -185,12 +200,61 @@ impl FromStr for Layout {
             "patchwork" => Ok(Layout::Patchwork),
             "sfdp" => Ok(Layout::Sfdp),
             "twopi" => Ok(Layout::Twopi),
-            other => Err(format!("Unknown layout variant '{}'", other)),
+            other => Err(Error::ParseFailed(format!("Unknown layout variant '{}'", other))),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Whether a graph's edges have a direction and whether parallel edges and self loops are allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum GraphKind {
+    /// `digraph`
+    #[default]
+    Directed,
+    /// `strict digraph`, no parallel edges or self loops
+    StrictDirected,
+    /// `graph`
+    Undirected,
+    /// `strict graph`, no parallel edges or self loops
+    StrictUndirected,
+}
+
+impl GraphKind {
+    pub fn is_directed(&self) -> bool {
+        matches!(self, GraphKind::Directed | GraphKind::StrictDirected)
+    }
+
+    pub fn is_strict(&self) -> bool {
+        matches!(self, GraphKind::StrictDirected | GraphKind::StrictUndirected)
+    }
+
+    /// the keyword(s) starting a DOT graph of this kind, e.g. `strict digraph`
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            GraphKind::Directed => "digraph",
+            GraphKind::StrictDirected => "strict digraph",
+            GraphKind::Undirected => "graph",
+            GraphKind::StrictUndirected => "strict graph",
+        }
+    }
+
+    /// the DOT edge operator, `->` for directed and `--` for undirected graphs
+    pub fn edge_op(&self) -> &'static str {
+        if self.is_directed() { "->" } else { "--" }
+    }
+}
+
+impl fmt::Display for GraphKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.keyword())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(
     feature = "serde",
     derive(serde_derive::Serialize, serde_derive::Deserialize)
@@ -296,8 +360,73 @@ impl fmt::Display for OutputFormat {
     }
 }
 
+impl OutputFormat {
+    /// the usual file extension for this format, without the leading dot
+    pub fn extension(&self) -> &'static str {
+        use OutputFormat::*;
+        match self {
+            Canon | Dot | Gv => "gv",
+            Xdot | Xdot12 | Xdot14 => "xdot",
+            DotJson | Json | Json0 | XdotJson => "json",
+            Cmap | Cmapx | CmapxNp | Imap | ImapNp | Ismap => "map",
+            Eps => "eps",
+            Fig => "fig",
+            Gd => "gd",
+            Gd2 => "gd2",
+            Gif => "gif",
+            Jpe | Jpeg | Jpg => "jpg",
+            Kitty | Kittyz => "kitty",
+            Pdf => "pdf",
+            Pic => "pic",
+            Plain | PlainExt => "txt",
+            Png => "png",
+            Pov => "pov",
+            Ps | Ps2 => "ps",
+            Svg | SvgInline => "svg",
+            Svgz => "svgz",
+            Tk => "tk",
+            Vrml => "wrl",
+            Vt | Vt24bit | Vt4up | Vt6up | Vt8up => "txt",
+            Wbmp => "wbmp",
+        }
+    }
+
+    /// the MIME type to serve this format with, e.g. in an HTTP `Content-Type` header
+    ///
+    /// `svgz` is reported as `image/svg+xml` and needs `Content-Encoding: gzip` alongside.
+    pub fn mime_type(&self) -> &'static str {
+        use OutputFormat::*;
+        match self {
+            Canon | Dot | Gv | Xdot | Xdot12 | Xdot14 => "text/vnd.graphviz",
+            DotJson | Json | Json0 | XdotJson => "application/json",
+            Cmap | Cmapx | CmapxNp => "text/html",
+            Eps | Ps | Ps2 => "application/postscript",
+            Fig => "application/x-xfig",
+            Gd | Gd2 => "application/octet-stream",
+            Gif => "image/gif",
+            Jpe | Jpeg | Jpg => "image/jpeg",
+            Pdf => "application/pdf",
+            Png => "image/png",
+            Svg | SvgInline | Svgz => "image/svg+xml",
+            Vrml => "model/vrml",
+            Wbmp => "image/vnd.wap.wbmp",
+            Imap | ImapNp | Ismap | Kitty | Kittyz | Pic | Plain | PlainExt | Pov | Tk | Vt
+            | Vt24bit | Vt4up | Vt6up | Vt8up => "text/plain",
+        }
+    }
+
+    /// returns `true` if the rendered output is binary data rather than UTF-8 text
+    pub fn is_binary(&self) -> bool {
+        use OutputFormat::*;
+        matches!(
+            self,
+            Gd | Gd2 | Gif | Jpe | Jpeg | Jpg | Pdf | Png | Svgz | Wbmp
+        )
+    }
+}
+
 impl FromStr for OutputFormat {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         match input.to_ascii_lowercase().as_str() {
@@ -346,25 +475,36 @@ impl FromStr for OutputFormat {
             "xdot1.2" | "xdot12" => Ok(OutputFormat::Xdot12),
             "xdot1.4" | "xdot14" => Ok(OutputFormat::Xdot14),
             "xdot_json" | "xdotjson" => Ok(OutputFormat::XdotJson),
-            other => Err(format!("Unknown output format variant '{}'", other)),
+            other => Err(Error::ParseFailed(format!("Unknown output format variant '{}'", other))),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum GraphErr {}
-
-impl fmt::Display for GraphErr {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {} // exhaustive match — unreachable since no variants
-    }
+/// The kinds of plugins graphviz loads, see `Context::plugins`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluginKind {
+    /// layout engines like `dot` and `neato`
+    Layout,
+    /// renderers, which draw a layout in a family of formats
+    Render,
+    /// devices, one per output format
+    Device,
+    /// text measurement, e.g. through pango
+    TextLayout,
+    /// image loaders for `image` attributes
+    LoadImage,
 }
 
-impl Error for GraphErr {}
-
-impl From<GraphErr> for String {
-    fn from(_: GraphErr) -> String {
-        "GraphErr (unreachable, empty enum)".to_string()
+impl fmt::Display for PluginKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            PluginKind::Layout => "layout",
+            PluginKind::Render => "render",
+            PluginKind::Device => "device",
+            PluginKind::TextLayout => "textlayout",
+            PluginKind::LoadImage => "loadimage",
+        };
+        write!(f, "{}", s)
     }
 }
 
@@ -381,7 +521,7 @@ pub trait CompatGraph {
         label: L,
         id: S,
         attributes: Vec<A>,
-    ) -> Result<String, GraphErr> {
+    ) -> Result<String, Error> {
         let id_ref = id.as_ref().to_string();
         let mut node = Self::Node::new(id_ref.clone(), label.as_ref().to_string());
         for attr in attributes {
@@ -397,7 +537,7 @@ pub trait CompatGraph {
         id: I,
         source: S,
         dest: D
-    ) -> Result<I, GraphErr> {
+    ) -> Result<I, Error> {
         debug_assert_ne!(source.as_ref(), dest.as_ref());
         let edge = Self::Edge::new(id.clone(), source.as_ref().to_string(), dest.as_ref().to_string());
         self.add_edge(edge);
@@ -482,4 +622,39 @@ pub trait CompatEdge {
 pub trait CompatCluster {
     fn new<S: AsRef<str>>(name: S) -> Self;
     fn set_attr<A: Into<NodeAttribute>>(&mut self, attr: A);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_kind() {
+        assert_eq!(GraphKind::default(), GraphKind::Directed);
+        assert_eq!(GraphKind::StrictUndirected.keyword(), "strict graph");
+        assert_eq!(GraphKind::Undirected.edge_op(), "--");
+        assert_eq!(GraphKind::StrictDirected.edge_op(), "->");
+        assert!(GraphKind::StrictDirected.is_strict() && GraphKind::StrictDirected.is_directed());
+        assert!(!GraphKind::Undirected.is_directed());
+    }
+
+    #[test]
+    fn test_output_format_helpers() {
+        assert_eq!(OutputFormat::Svg.extension(), "svg");
+        assert_eq!(OutputFormat::Jpeg.extension(), "jpg");
+        assert_eq!(OutputFormat::Cmapx.mime_type(), "text/html");
+        assert_eq!(OutputFormat::Png.mime_type(), "image/png");
+        assert!(OutputFormat::Png.is_binary());
+        assert!(!OutputFormat::Svg.is_binary());
+
+        let formats: std::collections::HashSet<_> =
+            [OutputFormat::Svg, OutputFormat::Png, OutputFormat::Svg].into_iter().collect();
+        assert_eq!(formats.len(), 2);
+    }
+
+    #[test]
+    fn test_plugin_kind_display() {
+        assert_eq!(PluginKind::Layout.to_string(), "layout");
+        assert_eq!(PluginKind::TextLayout.to_string(), "textlayout");
+    }
+}
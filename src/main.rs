@@ -8,7 +8,7 @@ use std::io::Write;
 use std::error::Error;
 
 // Adjust these use statements to your custom crate structure:
-use wrappedviz::{cgraph::Context, cgraph::Graph, Layout, OutputFormat};
+use wrappedviz::{cgraph::Context, cgraph::Graph, theme::GraphTheme, Layout, OutputFormat};
 
 #[derive(Debug, Clone, StructOpt)]
 pub struct Args {
@@ -19,6 +19,20 @@ pub struct Args {
     /// Output SVG file (optional)
     #[structopt(short, long)]
     output: Option<PathBuf>,
+
+    /// Theme file (JSON, or TOML with a `.toml` extension) applied before the layout
+    #[structopt(short, long)]
+    theme: Option<PathBuf>,
+}
+
+#[cfg(feature = "theme-files")]
+fn read_theme(path: &PathBuf) -> Result<GraphTheme, Box<dyn Error>> {
+    Ok(GraphTheme::from_file(path)?)
+}
+
+#[cfg(not(feature = "theme-files"))]
+fn read_theme(_path: &PathBuf) -> Result<GraphTheme, Box<dyn Error>> {
+    Err("theme files need the `theme-files` feature".into())
 }
 
 fn read_file(path: &PathBuf) -> Result<String, Box<dyn Error>> {
@@ -36,10 +50,18 @@ fn main() -> Result<(), Box<dyn Error>> {
     let context = Context::new();
 
     // Create graph from DOT string
-    let mut graph = Graph::new(content, &context);
+    let mut graph = Graph::parse(content, &context)?;
 
-    // Set layout engine (dot)
-    graph.set_layout(Layout::Dot).unwrap();
+    // Set layout engine, the theme's if one was given and dot otherwise
+    let layout = match &args.theme {
+        Some(path) => {
+            let theme = read_theme(path)?;
+            graph.apply_theme(&theme)?;
+            theme.layout
+        }
+        None => Layout::Dot,
+    };
+    graph.set_layout(layout)?;
 
     // Render to SVG
     let svg_slice = context.render(&graph, OutputFormat::Svg)?;
@@ -0,0 +1,382 @@
+//! A pool of rendering threads for multi threaded programs such as web services.
+//!
+//! [`Context`] wraps a raw `GVC_t` and can't leave the thread that created it, so
+//! [`RenderPool`] starts worker threads that each own a context and feeds them [`RenderJob`]s
+//! over a bounded queue. Every submitted job returns a [`RenderHandle`] that can be waited on,
+//! with or without a timeout, or awaited as a [`Future`].
+//!
+//! Graphviz keeps global state in its parser, layout engines and error handling and is not
+//! reentrant, so the workers take turns calling into it. Extra workers still help: they keep
+//! the caller's threads free and overlap DOT generation, queueing and copying with rendering.
+//! Other code that uses [`crate::cgraph`] directly while a pool is running must not run
+//! concurrently with it.
+//!
+//! ```no_run
+//! use wrappedviz::pool::{RenderJob, RenderPool};
+//! use wrappedviz::{Layout, OutputFormat};
+//!
+//! let pool = RenderPool::new(2, 64);
+//! let handle = pool
+//!     .submit(RenderJob::dot("digraph G { A -> B }", Layout::Dot, OutputFormat::Svg))
+//!     .unwrap();
+//! let svg = handle.wait().unwrap();
+//! ```
+
+use crate::cgraph::{Context, Graph};
+use crate::rgraph::RustGraph;
+use crate::trace::debug;
+use crate::{Error, Layout, OutputFormat};
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context as TaskContext, Poll, Waker};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// serializes every call into graphviz made by pool workers
+static GRAPHVIZ_LOCK: Mutex<()> = Mutex::new(());
+
+fn graphviz_lock() -> MutexGuard<'static, ()> {
+    // a panicking job leaves nothing behind that later jobs depend on
+    GRAPHVIZ_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A single render request: DOT source, the layout engine to run on it and the output format.
+#[derive(Debug, Clone)]
+pub struct RenderJob {
+    dot: String,
+    layout: Layout,
+    format: OutputFormat,
+    timeout: Option<Duration>,
+}
+
+impl RenderJob {
+    /// renders the first graph in `dot`
+    pub fn dot<S: Into<String>>(dot: S, layout: Layout, format: OutputFormat) -> Self {
+        Self {
+            dot: dot.into(),
+            layout,
+            format,
+            timeout: None,
+        }
+    }
+
+    /// renders a [`RustGraph`], converted to DOT on the calling thread
+    pub fn graph(graph: &RustGraph, layout: Layout, format: OutputFormat) -> Self {
+        Self::dot(graph.to_dot(), layout, format)
+    }
+
+    /// Gives up on the job once `timeout` passed since it was submitted.
+    ///
+    /// A job still waiting in the queue at that point is dropped without rendering, and
+    /// [`RenderHandle::wait`] returns [`Error::Timeout`]. Graphviz can't be interrupted, so a
+    /// job that already started rendering runs to completion in the background.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn run(&self, ctx: &Context) -> Result<Vec<u8>, Error> {
+        let mut graph = Graph::parse(&self.dot, ctx)?;
+        graph.set_layout(self.layout)?;
+        ctx.render(&graph, self.format)
+    }
+}
+
+/// the result slot shared between a worker and a [`RenderHandle`]
+#[derive(Default)]
+struct Slot {
+    result: Option<Result<Vec<u8>, Error>>,
+    /// set once the result was handed out, so it is only taken once
+    taken: bool,
+    waker: Option<Waker>,
+}
+
+type Shared = Arc<(Mutex<Slot>, Condvar)>;
+
+/// the worker's end of a [`RenderHandle`]
+struct Completion {
+    shared: Shared,
+    deadline: Option<Instant>,
+    done: bool,
+}
+
+impl Completion {
+    fn complete(&mut self, result: Result<Vec<u8>, Error>) {
+        let (slot, ready) = &*self.shared;
+        let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+        slot.result = Some(result);
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+        ready.notify_all();
+        self.done = true;
+    }
+
+    fn expired(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl Drop for Completion {
+    /// jobs dropped from the queue when the pool shuts down, or lost to a panicking worker
+    fn drop(&mut self) {
+        if !self.done {
+            self.complete(Err(Error::PoolClosed));
+        }
+    }
+}
+
+struct Task {
+    job: RenderJob,
+    completion: Completion,
+}
+
+/// The pending result of a job submitted to a [`RenderPool`].
+pub struct RenderHandle {
+    shared: Shared,
+    deadline: Option<Instant>,
+}
+
+impl RenderHandle {
+    /// Blocks until the job finished or its [timeout](RenderJob::timeout) passed.
+    pub fn wait(self) -> Result<Vec<u8>, Error> {
+        match self.deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                self.wait_timeout(remaining)
+            }
+            None => {
+                let (slot, ready) = &*self.shared;
+                let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+                while slot.result.is_none() && !slot.taken {
+                    slot = ready.wait(slot).unwrap_or_else(|e| e.into_inner());
+                }
+                take(&mut slot)
+            }
+        }
+    }
+
+    /// Blocks for at most `timeout`, returning [`Error::Timeout`] if the job has not finished by then.
+    pub fn wait_timeout(self, timeout: Duration) -> Result<Vec<u8>, Error> {
+        let (slot, ready) = &*self.shared;
+        let slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut slot, _) = ready
+            .wait_timeout_while(slot, timeout, |slot| slot.result.is_none() && !slot.taken)
+            .unwrap_or_else(|e| e.into_inner());
+        if slot.result.is_none() && !slot.taken {
+            return Err(Error::Timeout);
+        }
+        take(&mut slot)
+    }
+
+    /// Returns the result if the job already finished, without blocking.
+    ///
+    /// # Panics
+    /// Panics if the result was already returned by an earlier call.
+    pub fn try_wait(&mut self) -> Option<Result<Vec<u8>, Error>> {
+        let (slot, _) = &*self.shared;
+        let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+        if slot.result.is_none() && !slot.taken {
+            return None;
+        }
+        Some(take(&mut slot))
+    }
+}
+
+fn take(slot: &mut Slot) -> Result<Vec<u8>, Error> {
+    if slot.taken {
+        panic!("RenderHandle result was already taken");
+    }
+    slot.taken = true;
+    slot.result.take().expect("result is set before it is taken")
+}
+
+/// Resolves to the rendered bytes. The job's [timeout](RenderJob::timeout) only drops it from
+/// the queue, wrap the future in your runtime's timeout to stop waiting on a running job.
+impl Future for RenderHandle {
+    type Output = Result<Vec<u8>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let (slot, _) = &*self.shared;
+        let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+        if slot.result.is_some() || slot.taken {
+            return Poll::Ready(take(&mut slot));
+        }
+        slot.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// A fixed set of worker threads, each owning a [`Context`], that render submitted jobs.
+pub struct RenderPool {
+    sender: Option<SyncSender<Task>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl RenderPool {
+    /// Starts `workers` rendering threads (at least one) sharing a queue of at most
+    /// `queue_depth` waiting jobs.
+    pub fn new(workers: usize, queue_depth: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(queue_depth);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..workers.max(1))
+            .map(|id| {
+                let receiver = Arc::clone(&receiver);
+                std::thread::Builder::new()
+                    .name(format!("wrappedviz-render-{}", id))
+                    .spawn(move || worker(receiver))
+                    .expect("failed to spawn render worker")
+            })
+            .collect();
+        Self {
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    /// Queues `job` for rendering.
+    ///
+    /// # Errors
+    /// - [`Error::QueueFull`] if `queue_depth` jobs are already waiting.
+    /// - [`Error::PoolClosed`] if all workers are gone.
+    pub fn submit(&self, job: RenderJob) -> Result<RenderHandle, Error> {
+        let sender = self.sender.as_ref().ok_or(Error::PoolClosed)?;
+        let shared: Shared = Arc::default();
+        let deadline = job.timeout.map(|timeout| Instant::now() + timeout);
+        let task = Task {
+            job,
+            completion: Completion {
+                shared: Arc::clone(&shared),
+                deadline,
+                done: false,
+            },
+        };
+        match sender.try_send(task) {
+            Ok(()) => Ok(RenderHandle { shared, deadline }),
+            Err(TrySendError::Full(mut task)) => {
+                task.completion.done = true;
+                Err(Error::QueueFull)
+            }
+            Err(TrySendError::Disconnected(mut task)) => {
+                task.completion.done = true;
+                Err(Error::PoolClosed)
+            }
+        }
+    }
+
+    /// the number of worker threads
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+}
+
+impl Drop for RenderPool {
+    /// finishes all queued jobs, then stops the workers
+    fn drop(&mut self) {
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker(receiver: Arc<Mutex<Receiver<Task>>>) {
+    let ctx = {
+        let _lock = graphviz_lock();
+        Context::new()
+    };
+    loop {
+        let task = {
+            let receiver = receiver.lock().unwrap_or_else(|e| e.into_inner());
+            receiver.recv()
+        };
+        let Ok(Task { job, mut completion }) = task else {
+            break;
+        };
+        if completion.expired() {
+            debug!(format = %job.format, "render job timed out in queue");
+            completion.complete(Err(Error::Timeout));
+            continue;
+        }
+        let result = {
+            let _lock = graphviz_lock();
+            job.run(&ctx)
+        };
+        completion.complete(result);
+    }
+    let _lock = graphviz_lock();
+    drop(ctx);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rgraph;
+    use crate::{CompatEdge, CompatNode};
+
+    #[test]
+    fn test_pool_is_shareable_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        fn assert_send<T: Send>() {}
+        assert_send_sync::<RenderPool>();
+        assert_send::<RenderHandle>();
+    }
+
+    #[test]
+    fn test_pool_renders_dot_and_rust_graph_jobs() {
+        let pool = RenderPool::new(2, 8);
+
+        let svg = pool
+            .submit(RenderJob::dot("digraph G { A -> B }", Layout::Dot, OutputFormat::Svg))
+            .unwrap();
+
+        let graph = RustGraph::from_parts(
+            "G".into(),
+            vec![rgraph::Node::new("A", "A"), rgraph::Node::new("B", "B")],
+            vec![rgraph::Edge::new("A_to_B", "A", "B")],
+        );
+        let plain = pool
+            .submit(RenderJob::graph(&graph, Layout::Dot, OutputFormat::Plain))
+            .unwrap();
+
+        assert!(String::from_utf8(svg.wait().unwrap()).unwrap().contains("<svg"));
+        assert!(String::from_utf8(plain.wait().unwrap()).unwrap().starts_with("graph"));
+    }
+
+    #[test]
+    fn test_pool_reports_errors_per_job() {
+        let pool = RenderPool::new(1, 8);
+        let bad = pool
+            .submit(RenderJob::dot("digraph G {", Layout::Dot, OutputFormat::Svg))
+            .unwrap();
+        let good = pool
+            .submit(RenderJob::dot("digraph G { A }", Layout::Dot, OutputFormat::Svg))
+            .unwrap();
+        assert!(matches!(bad.wait(), Err(Error::InvalidDot(_))));
+        assert!(good.wait().is_ok());
+    }
+
+    #[test]
+    fn test_pool_queue_limit_and_timeout() {
+        let pool = RenderPool::new(1, 1);
+        // keep the only worker busy so the queue fills up
+        let lock = graphviz_lock();
+        let job = || RenderJob::dot("digraph G { A }", Layout::Dot, OutputFormat::Svg);
+        let mut handles = Vec::new();
+        let err = loop {
+            match pool.submit(job().timeout(Duration::from_millis(10))) {
+                Ok(handle) => handles.push(handle),
+                Err(e) => break e,
+            }
+            assert!(handles.len() <= 2, "queue depth is not enforced");
+        };
+        assert!(matches!(err, Error::QueueFull));
+
+        let waiting = handles.pop().unwrap();
+        assert!(matches!(waiting.wait(), Err(Error::Timeout)));
+        drop(lock);
+    }
+}
@@ -0,0 +1,499 @@
+//! Custom output formats written in Rust, plugged into graphviz's render pipeline.
+//!
+//! Implement [`Renderer`] and register it on a [`Context`](crate::cgraph::Context) under a format
+//! name with [`add_renderer`](crate::cgraph::Context::add_renderer). Rendering that format with
+//! [`render_custom`](crate::cgraph::Context::render_custom) then calls the renderer for every
+//! graph, cluster, node and edge and for each shape they are drawn with, after graphviz resolved
+//! their styles and colors. There is no SVG to post-process.
+//!
+//! ```no_run
+//! use std::io::{self, Write};
+//! use wrappedviz::Layout;
+//! use wrappedviz::cgraph::{Context, Graph};
+//! use wrappedviz::layout::Point;
+//! use wrappedviz::render::{RenderOutput, RenderStyle, Renderer};
+//!
+//! struct Outlines;
+//!
+//! impl Renderer for Outlines {
+//!     fn begin_node(&mut self, out: &mut RenderOutput<'_>, name: &str) -> io::Result<()> {
+//!         writeln!(out, "node {}", name)
+//!     }
+//!
+//!     fn polygon(&mut self, out: &mut RenderOutput<'_>, _: &RenderStyle, points: &[Point], _: bool) -> io::Result<()> {
+//!         writeln!(out, "polygon with {} corners", points.len())
+//!     }
+//! }
+//!
+//! let ctx = Context::new();
+//! ctx.add_renderer("outlines", Outlines).unwrap();
+//! let mut graph = Graph::parse("digraph G { a [shape=box] }", &ctx).unwrap();
+//! graph.layout(Layout::Dot).unwrap();
+//! let output = ctx.render_custom(&graph, "outlines").unwrap();
+//! ```
+
+use crate::Error;
+use crate::layout::{BoundingBox, Point};
+use crate::sys::*;
+use crate::xdot::TextAlign;
+
+use std::borrow::Cow;
+use std::ffi::{CStr, CString, c_char, c_int, c_uchar};
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+
+/// How the outline of a shape is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PenStyle {
+    /// no outline, e.g. `style=invis` or `penwidth=0`
+    None,
+    Dashed,
+    Dotted,
+    Solid,
+}
+
+impl From<c_int> for PenStyle {
+    fn from(pen: c_int) -> Self {
+        match pen {
+            0 => PenStyle::None,
+            1 => PenStyle::Dashed,
+            2 => PenStyle::Dotted,
+            _ => PenStyle::Solid,
+        }
+    }
+}
+
+/// The pen and fill a shape is drawn with, colors are `[r, g, b, a]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderStyle {
+    pub pen_color: [u8; 4],
+    pub fill_color: [u8; 4],
+    pub pen_width: f64,
+    pub pen: PenStyle,
+}
+
+/// A line of text, `color` is `[r, g, b, a]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan<'a> {
+    /// the anchor of the text on its baseline, see `align`
+    pub position: Point,
+    pub text: &'a str,
+    pub font: &'a str,
+    /// font size in points
+    pub size: f64,
+    pub color: [u8; 4],
+    pub align: TextAlign,
+}
+
+/// Receives the drawing operations of a render, see the [module docs](self).
+///
+/// Every method does nothing by default, implement the ones the format needs. Coordinates are
+/// in points with the y axis pointing up, like in the layout. The first error a method returns
+/// stops the renderer, later calls are skipped and the render fails with [`Error::Io`].
+/// A panic is reported the same way.
+pub trait Renderer {
+    fn begin_graph(&mut self, _out: &mut RenderOutput<'_>, _name: &str, _bb: BoundingBox) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end_graph(&mut self, _out: &mut RenderOutput<'_>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// `name` includes the `cluster_` prefix
+    fn begin_cluster(&mut self, _out: &mut RenderOutput<'_>, _name: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end_cluster(&mut self, _out: &mut RenderOutput<'_>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn begin_node(&mut self, _out: &mut RenderOutput<'_>, _name: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end_node(&mut self, _out: &mut RenderOutput<'_>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// called with the names of the edge's tail and head node
+    fn begin_edge(&mut self, _out: &mut RenderOutput<'_>, _tail: &str, _head: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end_edge(&mut self, _out: &mut RenderOutput<'_>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// an ellipse given by its center and its horizontal and vertical radii
+    fn ellipse(
+        &mut self,
+        _out: &mut RenderOutput<'_>,
+        _style: &RenderStyle,
+        _center: Point,
+        _rx: f64,
+        _ry: f64,
+        _filled: bool,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// a closed polygon
+    fn polygon(&mut self, _out: &mut RenderOutput<'_>, _style: &RenderStyle, _points: &[Point], _filled: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// a piecewise cubic bezier curve with `3n + 1` control points, e.g. an edge
+    fn beziercurve(
+        &mut self,
+        _out: &mut RenderOutput<'_>,
+        _style: &RenderStyle,
+        _points: &[Point],
+        _filled: bool,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// an open polyline
+    fn polyline(&mut self, _out: &mut RenderOutput<'_>, _style: &RenderStyle, _points: &[Point]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// a line of a label
+    fn textspan(&mut self, _out: &mut RenderOutput<'_>, _span: &TextSpan<'_>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The output of a render. Writes go through graphviz, so they end up wherever the render goes.
+pub struct RenderOutput<'j> {
+    job: *mut GVJ_t,
+    _job: PhantomData<&'j mut GVJ_t>,
+}
+
+impl Write for RenderOutput<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = unsafe { rust_render_write(self.job, buf.as_ptr() as *const c_char, buf.len()) };
+        if written == 0 && !buf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "graphviz could not write the render output"));
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A renderer registered on a context and the first error it returned.
+///
+/// Graphviz keeps pointers to the slot and the plugin tables behind `handle` until the context
+/// is freed, so a slot lives behind a raw pointer from [`register`](Self::register) and must be
+/// released with [`free`](Self::free) after `gvFreeContext`.
+pub(crate) struct RendererSlot {
+    format: String,
+    renderer: Box<dyn Renderer>,
+    error: Option<io::Error>,
+    handle: *mut libc::c_void,
+}
+
+impl RendererSlot {
+    /// registers `renderer` as the render and device plugin for `format` on `gvc`
+    pub(crate) fn register(gvc: *mut GVC_t, format: &str, renderer: Box<dyn Renderer>) -> Result<*mut Self, Error> {
+        let c_format = CString::new(format)?;
+        // graphviz's pointer and ours share the allocation, moving a `Box` would invalidate it
+        let slot = Box::into_raw(Box::new(Self {
+            format: format.to_string(),
+            renderer,
+            error: None,
+            handle: std::ptr::null_mut(),
+        }));
+        let handle = unsafe { rust_add_renderer(gvc, c_format.as_ptr(), slot as *mut libc::c_void, &OPS) };
+        if handle.is_null() {
+            unsafe { Self::free(slot) };
+            return Err(Error::PluginFailed(format.to_string()));
+        }
+        unsafe { (*slot).handle = handle };
+        Ok(slot)
+    }
+
+    /// drops a slot returned by [`register`](Self::register)
+    ///
+    /// # Safety
+    /// `slot` must come from `register` and graphviz must no longer use it, i.e. the context it
+    /// was registered on is freed.
+    pub(crate) unsafe fn free(slot: *mut Self) {
+        drop(unsafe { Box::from_raw(slot) });
+    }
+
+    pub(crate) fn format(&self) -> &str {
+        &self.format
+    }
+
+    /// the error that stopped the last render, resetting the renderer for the next one
+    pub(crate) fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+impl Drop for RendererSlot {
+    fn drop(&mut self) {
+        unsafe { rust_free_renderer(self.handle) };
+    }
+}
+
+static OPS: rust_render_ops = rust_render_ops {
+    begin_graph: Some(begin_graph),
+    end_graph: Some(end_graph),
+    begin_cluster: Some(begin_cluster),
+    end_cluster: Some(end_cluster),
+    begin_node: Some(begin_node),
+    end_node: Some(end_node),
+    begin_edge: Some(begin_edge),
+    end_edge: Some(end_edge),
+    ellipse: Some(ellipse),
+    polygon: Some(polygon),
+    beziercurve: Some(beziercurve),
+    polyline: Some(polyline),
+    textspan: Some(textspan),
+};
+
+/// runs `f` with the renderer behind `cookie` unless it already failed, keeping the first error
+unsafe fn with_renderer(
+    cookie: *mut libc::c_void,
+    job: *mut GVJ_t,
+    f: impl FnOnce(&mut dyn Renderer, &mut RenderOutput<'_>) -> io::Result<()>,
+) {
+    let slot = unsafe { &mut *(cookie as *mut RendererSlot) };
+    if slot.error.is_some() {
+        return;
+    }
+    let mut out = RenderOutput { job, _job: PhantomData };
+    let renderer = &mut *slot.renderer;
+    slot.error = match panic::catch_unwind(AssertUnwindSafe(|| f(renderer, &mut out))) {
+        Ok(result) => result.err(),
+        Err(_) => Some(io::Error::other(format!("renderer for '{}' panicked", slot.format))),
+    };
+}
+
+unsafe fn text<'a>(s: *const c_char) -> Cow<'a, str> {
+    if s.is_null() {
+        return Cow::Borrowed("");
+    }
+    unsafe { CStr::from_ptr(s) }.to_string_lossy()
+}
+
+unsafe fn points(points: *const pointf, n: usize) -> Vec<Point> {
+    if points.is_null() {
+        return Vec::new();
+    }
+    unsafe { std::slice::from_raw_parts(points, n) }
+        .iter()
+        .map(|&p| p.into())
+        .collect()
+}
+
+unsafe fn style(style: *const rust_render_style) -> RenderStyle {
+    let style = unsafe { &*style };
+    RenderStyle {
+        pen_color: style.pencolor,
+        fill_color: style.fillcolor,
+        pen_width: style.penwidth,
+        pen: style.pen.into(),
+    }
+}
+
+unsafe extern "C" fn begin_graph(cookie: *mut libc::c_void, job: *mut GVJ_t, name: *const c_char, bb: boxf) {
+    let name = unsafe { text(name) };
+    unsafe { with_renderer(cookie, job, |r, out| r.begin_graph(out, &name, bb.into())) }
+}
+
+unsafe extern "C" fn end_graph(cookie: *mut libc::c_void, job: *mut GVJ_t) {
+    unsafe { with_renderer(cookie, job, |r, out| r.end_graph(out)) }
+}
+
+unsafe extern "C" fn begin_cluster(cookie: *mut libc::c_void, job: *mut GVJ_t, name: *const c_char) {
+    let name = unsafe { text(name) };
+    unsafe { with_renderer(cookie, job, |r, out| r.begin_cluster(out, &name)) }
+}
+
+unsafe extern "C" fn end_cluster(cookie: *mut libc::c_void, job: *mut GVJ_t) {
+    unsafe { with_renderer(cookie, job, |r, out| r.end_cluster(out)) }
+}
+
+unsafe extern "C" fn begin_node(cookie: *mut libc::c_void, job: *mut GVJ_t, name: *const c_char) {
+    let name = unsafe { text(name) };
+    unsafe { with_renderer(cookie, job, |r, out| r.begin_node(out, &name)) }
+}
+
+unsafe extern "C" fn end_node(cookie: *mut libc::c_void, job: *mut GVJ_t) {
+    unsafe { with_renderer(cookie, job, |r, out| r.end_node(out)) }
+}
+
+unsafe extern "C" fn begin_edge(cookie: *mut libc::c_void, job: *mut GVJ_t, tail: *const c_char, head: *const c_char) {
+    let (tail, head) = unsafe { (text(tail), text(head)) };
+    unsafe { with_renderer(cookie, job, |r, out| r.begin_edge(out, &tail, &head)) }
+}
+
+unsafe extern "C" fn end_edge(cookie: *mut libc::c_void, job: *mut GVJ_t) {
+    unsafe { with_renderer(cookie, job, |r, out| r.end_edge(out)) }
+}
+
+/// graphviz passes an ellipse as its center and a corner of its bounding box
+unsafe extern "C" fn ellipse(
+    cookie: *mut libc::c_void,
+    job: *mut GVJ_t,
+    style_ptr: *const rust_render_style,
+    points_ptr: *const pointf,
+    filled: c_int,
+) {
+    let (style, points) = unsafe { (style(style_ptr), points(points_ptr, 2)) };
+    let (center, corner) = (points[0], points[1]);
+    let (rx, ry) = (corner.x - center.x, corner.y - center.y);
+    unsafe { with_renderer(cookie, job, |r, out| r.ellipse(out, &style, center, rx, ry, filled != 0)) }
+}
+
+unsafe extern "C" fn polygon(
+    cookie: *mut libc::c_void,
+    job: *mut GVJ_t,
+    style_ptr: *const rust_render_style,
+    points_ptr: *const pointf,
+    n: usize,
+    filled: c_int,
+) {
+    let (style, points) = unsafe { (style(style_ptr), points(points_ptr, n)) };
+    unsafe { with_renderer(cookie, job, |r, out| r.polygon(out, &style, &points, filled != 0)) }
+}
+
+unsafe extern "C" fn beziercurve(
+    cookie: *mut libc::c_void,
+    job: *mut GVJ_t,
+    style_ptr: *const rust_render_style,
+    points_ptr: *const pointf,
+    n: usize,
+    filled: c_int,
+) {
+    let (style, points) = unsafe { (style(style_ptr), points(points_ptr, n)) };
+    unsafe { with_renderer(cookie, job, |r, out| r.beziercurve(out, &style, &points, filled != 0)) }
+}
+
+unsafe extern "C" fn polyline(
+    cookie: *mut libc::c_void,
+    job: *mut GVJ_t,
+    style_ptr: *const rust_render_style,
+    points_ptr: *const pointf,
+    n: usize,
+) {
+    let (style, points) = unsafe { (style(style_ptr), points(points_ptr, n)) };
+    unsafe { with_renderer(cookie, job, |r, out| r.polyline(out, &style, &points)) }
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn textspan(
+    cookie: *mut libc::c_void,
+    job: *mut GVJ_t,
+    position: pointf,
+    text_ptr: *const c_char,
+    font: *const c_char,
+    size: f64,
+    color: *const c_uchar,
+    just: c_char,
+) {
+    let (content, font) = unsafe { (text(text_ptr), text(font)) };
+    let color = if color.is_null() { [0, 0, 0, 255] } else { unsafe { *(color as *const [u8; 4]) } };
+    let align = match just as u8 {
+        b'l' => TextAlign::Left,
+        b'r' => TextAlign::Right,
+        _ => TextAlign::Center,
+    };
+    let span = TextSpan {
+        position: position.into(),
+        text: &content,
+        font: &font,
+        size,
+        color,
+        align,
+    };
+    unsafe { with_renderer(cookie, job, |r, out| r.textspan(out, &span)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Layout;
+    use crate::cgraph::{Context, Graph};
+
+    /// writes one line per call
+    struct Trace;
+
+    impl Renderer for Trace {
+        fn begin_node(&mut self, out: &mut RenderOutput<'_>, name: &str) -> io::Result<()> {
+            writeln!(out, "node {}", name)
+        }
+
+        fn begin_edge(&mut self, out: &mut RenderOutput<'_>, tail: &str, head: &str) -> io::Result<()> {
+            writeln!(out, "edge {} {}", tail, head)
+        }
+
+        fn polygon(&mut self, out: &mut RenderOutput<'_>, style: &RenderStyle, points: &[Point], _: bool) -> io::Result<()> {
+            writeln!(out, "polygon {} {:?}", points.len(), style.pen_color)
+        }
+
+        fn textspan(&mut self, out: &mut RenderOutput<'_>, span: &TextSpan<'_>) -> io::Result<()> {
+            writeln!(out, "text {}", span.text)
+        }
+    }
+
+    struct Failing;
+
+    impl Renderer for Failing {
+        fn begin_node(&mut self, _: &mut RenderOutput<'_>, _: &str) -> io::Result<()> {
+            Err(io::Error::other("no nodes please"))
+        }
+    }
+
+    /// fails on the first node of its first render only
+    struct FailsOnce {
+        failed: bool,
+    }
+
+    impl Renderer for FailsOnce {
+        fn begin_node(&mut self, out: &mut RenderOutput<'_>, name: &str) -> io::Result<()> {
+            if !self.failed {
+                self.failed = true;
+                return Err(io::Error::other("first render fails"));
+            }
+            writeln!(out, "node {}", name)
+        }
+    }
+
+    #[test]
+    fn test_custom_renderer() {
+        let ctx = Context::new();
+        ctx.add_renderer("trace", Trace).unwrap();
+        ctx.add_renderer("failing", Failing).unwrap();
+        ctx.add_renderer("once", FailsOnce { failed: false }).unwrap();
+        assert!(matches!(ctx.add_renderer("trace", Trace), Err(Error::PluginFailed(_))));
+
+        let mut graph = Graph::parse("digraph G { a [shape=box, color=red]; a -> b }", &ctx).unwrap();
+        graph.layout(Layout::Dot).unwrap();
+
+        let output = String::from_utf8(ctx.render_custom(&graph, "trace").unwrap()).unwrap();
+        assert!(output.contains("node a\n"));
+        assert!(output.contains("polygon 4 [255, 0, 0, 255]\n"));
+        assert!(output.contains("text b\n"));
+        assert!(output.contains("edge a b\n"));
+
+        let err = ctx.render_custom(&graph, "failing").unwrap_err();
+        assert!(matches!(err, Error::Io(e) if e.to_string() == "no nodes please"));
+        assert!(ctx.render_custom(&graph, "trace").is_ok());
+
+        // the error doesn't stick to the next render
+        let err = ctx.render_custom(&graph, "once").unwrap_err();
+        assert!(matches!(err, Error::Io(e) if e.to_string() == "first render fails"));
+        let output = String::from_utf8(ctx.render_custom(&graph, "once").unwrap()).unwrap();
+        assert!(output.contains("node a\n"));
+    }
+}
@@ -6,11 +6,12 @@
 pub mod wasm;
 #[cfg(target_arch = "wasm32")]
 pub use wasm::*;
-use std::collections::HashMap;
-use crate::{CompatNode, GraphExt, CompatEdge, CompatCluster, CompatGraph};
+use std::collections::{HashMap, HashSet};
+use crate::{CompatNode, GraphExt, CompatEdge, CompatCluster, CompatGraph, Error, GraphKind};
 use uuid::Uuid;
 use std::fmt::Write;
-use crate::style::{EdgeAttribute, Attribute, GraphAttr, NodeAttribute, CommonAttr};
+use crate::style::{label_ports, ClusterAttribute, EdgeAttribute, Attribute, EdgeAttr, GraphAttr, NodeAttr, NodeAttribute, CommonAttr, Port};
+use crate::theme::GraphTheme;
 /// a module for incrementally editing graphs using a domain specific structured query language.
 //pub mod command;
 #[derive(Debug, Clone, PartialEq)]
@@ -50,21 +51,53 @@ impl Node {
         &self.id
     }
 
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn attributes(&self) -> &[NodeAttribute] {
+        &self.attributes
+    }
+
     pub fn has_class(&self, classname: &str) -> bool {
         self.has_attr(CommonAttr::Class(classname.to_string()))
     }
+
+    /// the label the node is drawn with and whether it is HTML-like: an HTML label attribute,
+    /// else a label attribute (e.g. a record label), else the node's own label
+    fn effective_label(&self) -> (String, bool) {
+        let html = self.attributes.iter().rev().find_map(|attr| match attr {
+            NodeAttribute::Common(CommonAttr::HtmlLabel(label)) => Some(label.to_string()),
+            _ => None,
+        });
+        if let Some(html) = html {
+            return (html, true);
+        }
+        let text = self.attributes.iter().rev().find_map(|attr| match attr {
+            NodeAttribute::Common(CommonAttr::Label(label)) => Some(label.clone()),
+            _ => None,
+        });
+        (text.unwrap_or_else(|| self.label.clone()), false)
+    }
+
+    /// the port names defined by the node's record or HTML-like label
+    pub fn ports(&self) -> Vec<String> {
+        let (label, html) = self.effective_label();
+        label_ports(&label, html)
+    }
 }
 
 impl std::fmt::Display for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "\"{}\" [id=\"{}\", label=\"{}\"",
-            self.id, self.id, self.label
-        )?;
+        write!(f, "\"{}\" [id=\"{}\", ", self.id, self.id)?;
+        match self.effective_label() {
+            (label, true) => write!(f, "label=<{}>", label)?,
+            (label, false) => write!(f, "label=\"{}\"", label)?,
+        }
         for attr in &self.attributes {
             if let NodeAttribute::Common(CommonAttr::Id(_))
-                | NodeAttribute::Common(CommonAttr::Label(_)) = attr
+                | NodeAttribute::Common(CommonAttr::Label(_))
+                | NodeAttribute::Common(CommonAttr::HtmlLabel(_)) = attr
             {
                 // skip duplicate id/label
                 continue;
@@ -99,6 +132,12 @@ impl std::fmt::Display for ParseNodeError {
 }
 impl std::error::Error for ParseNodeError {}
 
+impl From<ParseNodeError> for Error {
+    fn from(e: ParseNodeError) -> Self {
+        Error::ParseFailed(e.to_string())
+    }
+}
+
 impl FromStr for Node {
     type Err = ParseNodeError;
 
@@ -194,6 +233,20 @@ impl Edge {
         &self.id
     }
 
+    /// the id of the tail node
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// the id of the head node
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    pub fn attributes(&self) -> &[EdgeAttribute] {
+        &self.attributes
+    }
+
     pub fn create(source: String, dest: String) -> Self {
         Self {
             id: format!("{}_{}", source, dest),
@@ -225,13 +278,13 @@ impl CompatEdge for Edge {
     }
 }
 
-impl std::fmt::Display for Edge {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        debug_assert_ne!(self.source, self.dest);
+impl Edge {
+    /// writes the edge with the given edge operator, `->` or `--`
+    fn fmt_with_op(&self, f: &mut std::fmt::Formatter<'_>, op: &str) -> std::fmt::Result {
         write!(
             f,
-            "\"{}\" -> \"{}\" [id=\"{}\"",
-            self.source, self.dest, self.id
+            "\"{}\" {} \"{}\" [id=\"{}\"",
+            self.source, op, self.dest, self.id
         )?;
         for attr in &self.attributes {
             if let EdgeAttribute::Common(CommonAttr::Id(_))
@@ -246,6 +299,24 @@ impl std::fmt::Display for Edge {
     }
 }
 
+/// displays an edge with the edge operator of the graph it is written into
+struct EdgeDot<'a> {
+    edge: &'a Edge,
+    kind: GraphKind,
+}
+
+impl std::fmt::Display for EdgeDot<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.edge.fmt_with_op(f, self.kind.edge_op())
+    }
+}
+
+impl std::fmt::Display for Edge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_op(f, "->")
+    }
+}
+
 use std::str::FromStr;
 
 #[derive(Debug)]
@@ -273,16 +344,29 @@ impl std::fmt::Display for ParseEdgeError {
 
 impl std::error::Error for ParseEdgeError {}
 
+impl From<ParseEdgeError> for Error {
+    fn from(e: ParseEdgeError) -> Self {
+        Error::ParseFailed(e.to_string())
+    }
+}
+
 impl FromStr for Edge {
     type Err = ParseEdgeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let s = s.trim();
 
-        // Split source -> dest
-        let arrow_split: Vec<&str> = s.splitn(2, "->").collect();
+        // Split source -> dest, or source -- dest for undirected graphs, whichever comes first
+        // before the attribute list, so an arrow inside a label doesn't count
+        let endpoints = s.split('[').next().unwrap_or(s);
+        let op = match (endpoints.find("->"), endpoints.find("--")) {
+            (Some(arrow), Some(line)) if line < arrow => "--",
+            (None, Some(_)) => "--",
+            _ => "->",
+        };
+        let arrow_split: Vec<&str> = s.splitn(2, op).collect();
         if arrow_split.len() != 2 {
-            return Err(ParseEdgeError::ParseError("missing '->'".into()));
+            return Err(ParseEdgeError::ParseError("missing '->' or '--'".into()));
         }
 
         let source_str = arrow_split[0].trim();
@@ -413,24 +497,92 @@ fn test_node_display_and_parse() {
 #[derive(Debug, Clone, PartialEq)]
 pub struct RustGraph {
     name: String,
+    kind: GraphKind,
     nodes: HashMap<String, Node>,
     edges: HashMap<String, Edge>,
     clusters: HashMap<String, RustGraph>,
     attributes: Vec<GraphAttr>,
+    /// written as `node [...]`, overridden by the nodes' own attributes
+    node_defaults: Vec<NodeAttr>,
+    /// written as `edge [...]`, overridden by the edges' own attributes
+    edge_defaults: Vec<EdgeAttr>,
+    /// labels and styling of the box drawn around a cluster, also written for the root graph
+    cluster_attrs: Vec<ClusterAttribute>,
 }
 
 impl RustGraph {
-    /// Create a new RustGraph with the given name
+    /// Create a new directed RustGraph with the given name
     pub fn new(name: String) -> Self {
+        Self::with_kind(name, GraphKind::Directed)
+    }
+
+    /// Create a new RustGraph of the given kind, e.g. an undirected or strict graph
+    pub fn with_kind(name: String, kind: GraphKind) -> Self {
         Self {
             name,
+            kind,
             nodes: HashMap::new(),
             edges: HashMap::new(),
             attributes: vec![],
             clusters: HashMap::new(),
+            node_defaults: vec![],
+            edge_defaults: vec![],
+            cluster_attrs: vec![],
         }
     }
 
+    /// whether the graph is directed and/or strict
+    pub fn kind(&self) -> GraphKind {
+        self.kind
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn node(&self, id: &str) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    pub fn edge(&self, id: &str) -> Option<&Edge> {
+        self.edges.get(id)
+    }
+
+    /// the graph's nodes in no particular order
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.values()
+    }
+
+    /// the graph's edges in no particular order
+    pub fn edges(&self) -> impl Iterator<Item = &Edge> {
+        self.edges.values()
+    }
+
+    /// the clusters directly inside this graph, keyed by their `cluster_` prefixed name
+    pub fn clusters(&self) -> impl Iterator<Item = (&str, &RustGraph)> {
+        self.clusters.iter().map(|(name, cluster)| (name.as_str(), cluster))
+    }
+
+    pub fn attributes(&self) -> &[GraphAttr] {
+        &self.attributes
+    }
+
+    pub fn node_defaults(&self) -> &[NodeAttr] {
+        &self.node_defaults
+    }
+
+    pub fn edge_defaults(&self) -> &[EdgeAttr] {
+        &self.edge_defaults
+    }
+
+    pub fn cluster_attributes(&self) -> &[ClusterAttribute] {
+        &self.cluster_attrs
+    }
+
+    pub fn set_kind(&mut self, kind: GraphKind) {
+        self.kind = kind;
+    }
+
     pub fn from_parts(name: String, nodes: Vec<Node>, edges: Vec<Edge>) -> Self {
         let mut newgraph = RustGraph::new(name);
         for node in nodes.into_iter() {
@@ -455,28 +607,93 @@ impl RustGraph {
     }*/
 
     /// Add an attribute to an existing node
-    pub fn add_node_attr<A: Attribute + Into<NodeAttribute>>(&mut self, node_id: String, attr: A) {
-        if let Some(node) = self.nodes.get_mut(&node_id) {
-            node.attributes.push(attr.into());
-        } else {
-            eprintln!("Node with id {:?} not found", node_id);
-        }
+    ///
+    /// # Errors
+    /// Returns [`Error::NodeNotFound`] if no node with `node_id` exists.
+    pub fn add_node_attr<A: Attribute + Into<NodeAttribute>>(&mut self, node_id: String, attr: A) -> Result<(), Error> {
+        let node = self.nodes.get_mut(&node_id).ok_or(Error::NodeNotFound(node_id))?;
+        node.attributes.push(attr.into());
+        Ok(())
     }
 
     /// Add an attribute to an existing edge
-    pub fn add_edge_attr<A: Attribute + Into<EdgeAttribute>>(&mut self, edge_id: String, attr: A) {
+    ///
+    /// # Errors
+    /// Returns [`Error::EdgeNotFound`] if no edge with `edge_id` exists.
+    pub fn add_edge_attr<A: Attribute + Into<EdgeAttribute>>(&mut self, edge_id: String, attr: A) -> Result<(), Error> {
+        let edge = self.edges.get(&edge_id).ok_or_else(|| Error::EdgeNotFound(edge_id.clone()))?;
+        let attr = attr.into();
+        match &attr {
+            EdgeAttribute::EdgeAttr(EdgeAttr::TailPort(port)) => self.check_port(&edge.source, port)?,
+            EdgeAttribute::EdgeAttr(EdgeAttr::HeadPort(port)) => self.check_port(&edge.dest, port)?,
+            _ => {}
+        }
         if let Some(edge) = self.edges.get_mut(&edge_id) {
-            edge.attributes.push(attr.into());
+            edge.attributes.push(attr);
+        }
+        Ok(())
+    }
+
+    /// makes sure a named port exists in the label of the node `node_id`
+    fn check_port(&self, node_id: &str, port: &Port) -> Result<(), Error> {
+        let Some(name) = &port.name else {
+            return Ok(());
+        };
+        let node = self
+            .nodes
+            .get(node_id)
+            .ok_or_else(|| Error::NodeNotFound(node_id.to_string()))?;
+        if node.ports().contains(name) {
+            Ok(())
         } else {
-            eprintln!("Edge with id {:?} not found", edge_id);
+            Err(Error::PortNotFound { node: node_id.to_string(), port: name.clone() })
         }
     }
 
+    /// Adds a label or style for the box drawn around this graph when it is used as a cluster.
+    pub fn add_cluster_attr<A: Into<ClusterAttribute>>(&mut self, attr: A) {
+        self.cluster_attrs.push(attr.into());
+    }
+
     /// Add a graph-level attribute
     pub fn add_graph_attr<A: Attribute + Into<GraphAttr>>(&mut self, attr: A) {
         self.attributes.push(attr.into());
     }
 
+    /// Sets the default of a node attribute, replacing an earlier default for the same attribute.
+    ///
+    /// Nodes that set the attribute themselves keep their own value.
+    pub fn set_default_node_attr(&mut self, attr: NodeAttr) {
+        self.node_defaults.retain(|a| a.attr_name() != attr.attr_name());
+        self.node_defaults.push(attr);
+    }
+
+    /// Sets the default of an edge attribute, replacing an earlier default for the same attribute.
+    ///
+    /// Edges that set the attribute themselves keep their own value.
+    pub fn set_default_edge_attr(&mut self, attr: EdgeAttr) {
+        self.edge_defaults.retain(|a| a.attr_name() != attr.attr_name());
+        self.edge_defaults.push(attr);
+    }
+
+    /// Sets a graph attribute, replacing an earlier value of the same attribute.
+    pub fn set_default_graph_attr(&mut self, attr: GraphAttr) {
+        self.attributes.retain(|a| a.attr_name() != attr.attr_name());
+        self.attributes.push(attr);
+    }
+
+    /// Applies `theme`: sets its layout engine and makes its node and edge attributes
+    /// the defaults of this graph.
+    pub fn apply_theme(&mut self, theme: &GraphTheme) {
+        self.set_default_graph_attr(GraphAttr::Layout(theme.layout));
+        for attr in &theme.node_attrs {
+            self.set_default_node_attr(attr.clone());
+        }
+        for attr in &theme.edge_attrs {
+            self.set_default_edge_attr(attr.clone());
+        }
+    }
+
     /// Creates a new cluster subgraph within this graph.
     ///
     /// # Parameters
@@ -487,7 +704,7 @@ impl RustGraph {
     ///
     /// # Errors
     /// Returns `Err` if the cluster could not be created (e.g., due to invalid name or allocation failure).
-    pub fn add_cluster(&mut self, graph: RustGraph) -> Result<String, String> {
+    pub fn add_cluster(&mut self, graph: RustGraph) -> Result<String, Error> {
         // Prepend "cluster_" to comply with Graphviz convention
         let cluster_name = format!("cluster_{}", graph.name);
         self.clusters.insert(cluster_name.clone(), graph);
@@ -495,29 +712,51 @@ impl RustGraph {
         Ok(cluster_name)
     }
 
-    /// converts the RustGraph to graphviz dot structure with appropriate attributes.
-    /// please note this impl currently doesn't add cluster's that will be imnplemented later.
+    /// converts the RustGraph to graphviz dot structure with appropriate attributes,
+    /// clusters included.
     pub fn to_dot(&self) -> String {
         let mut dot = String::new();
-        dot.push_str("digraph G {\n");
+        writeln!(&mut dot, "{} {} {{", self.kind.keyword(), dot_id(&self.name)).unwrap();
+        writeln!(&mut dot, "    overlap=false").unwrap();
+        self.write_body(&mut dot, self, 1, &mut HashSet::new());
+        dot.push_str("}\n");
+        dot
+    }
 
+    /// writes attributes, nodes, edges and clusters, leaving out the edges in `written`
+    /// so an edge shared by the root and nested clusters isn't duplicated
+    fn write_body<'a>(&'a self, dot: &mut String, root: &RustGraph, depth: usize, written: &mut HashSet<&'a str>) {
+        let indent = "    ".repeat(depth);
         for attr in self.attributes.iter() {
-            writeln!(&mut dot, "    {}", attr).unwrap();
+            writeln!(dot, "{indent}{}", attr).unwrap();
+        }
+        for attr in self.cluster_attrs.iter() {
+            writeln!(dot, "{indent}{}", attr).unwrap();
         }
 
-        writeln!(&mut dot, "    overlap=false").unwrap();
+        if !self.node_defaults.is_empty() {
+            writeln!(dot, "{indent}node [{}]", join_attrs(&self.node_defaults)).unwrap();
+        }
+        if !self.edge_defaults.is_empty() {
+            writeln!(dot, "{indent}edge [{}]", join_attrs(&self.edge_defaults)).unwrap();
+        }
 
-        for (_id, node) in &self.nodes {
-            writeln!(&mut dot, "    {}", node).unwrap();
+        for node in self.nodes.values() {
+            writeln!(dot, "{indent}{}", node).unwrap();
         }
 
-        for (_id, edge) in &self.edges {
-            debug_assert_ne!(edge.source, edge.dest);
-            writeln!(&mut dot, "    {}", edge).unwrap();
+        for (id, edge) in &self.edges {
+            if !written.insert(id.as_str()) {
+                continue;
+            }
+            writeln!(dot, "{indent}{}", EdgeDot { edge, kind: root.kind }).unwrap();
         }
 
-        dot.push_str("}\n");
-        dot
+        for (name, cluster) in &self.clusters {
+            writeln!(dot, "{indent}subgraph {} {{", dot_id(name)).unwrap();
+            cluster.write_body(dot, root, depth + 1, written);
+            writeln!(dot, "{indent}}}").unwrap();
+        }
     }
 
     pub fn nodes_by_class<'a>(
@@ -530,6 +769,28 @@ impl RustGraph {
     }
 }
 
+fn join_attrs<A: std::fmt::Display>(attrs: &[A]) -> String {
+    attrs.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// words DOT reserves, in any case, they can only be used as ids when quoted
+const DOT_KEYWORDS: [&str; 6] = ["node", "edge", "graph", "digraph", "subgraph", "strict"];
+
+/// quotes `name` unless it is a plain DOT identifier that isn't a keyword
+fn dot_id(name: &str) -> std::borrow::Cow<'_, str> {
+    let mut chars = name.chars();
+    let plain = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !DOT_KEYWORDS.iter().any(|keyword| keyword.eq_ignore_ascii_case(name));
+    if plain {
+        name.into()
+    } else {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\"")).into()
+    }
+}
+
 pub struct Cluster {}
 impl CompatCluster for Cluster {
     fn new<S: AsRef<str>>(_name: S) -> Self {
@@ -549,10 +810,14 @@ impl CompatGraph for RustGraph {
     fn new<S: AsRef<str>, A: Attribute + Into<GraphAttr>>(name: S, attributes: Vec<A>) -> Self {
         RustGraph {
             name: name.as_ref().to_string(),
+            kind: GraphKind::Directed,
             nodes: HashMap::new(),
             edges: HashMap::new(),
             clusters: HashMap::new(),
             attributes: attributes.into_iter().map(|a| a.into()).collect(),
+            node_defaults: vec![],
+            edge_defaults: vec![],
+            cluster_attrs: vec![],
         }
     }
 
@@ -562,7 +827,6 @@ impl CompatGraph for RustGraph {
 
     fn add_edge<E: Into<Self::Edge>>(&mut self, edge: E) {
         let edge = edge.into();
-        debug_assert_ne!(edge.source, edge.dest);
         self.edges.insert(edge.id().to_string(), edge);
     }
 
@@ -589,6 +853,110 @@ impl GraphExt for RustGraph {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_apply_theme() {
+        let node = Node {
+            id: "A".into(),
+            label: "A".into(),
+            attributes: vec![NodeAttr::Color("#00ff00".parse().unwrap()).into()],
+        };
+        let mut graph = RustGraph::from_parts("G".into(), vec![node], vec![]);
+        let theme = GraphTheme {
+            name: "dark".into(),
+            layout: crate::Layout::Neato,
+            node_attrs: vec![NodeAttr::Color("#ffffff".parse().unwrap())],
+            edge_attrs: vec![EdgeAttr::Color("#ff0000".parse().unwrap())],
+        };
+        graph.apply_theme(&theme);
+        graph.apply_theme(&theme);
+
+        let dot = graph.to_dot();
+        assert_eq!(dot.matches("layout=").count(), 1);
+        assert!(dot.contains("layout=\"neato\""));
+        assert!(dot.contains("    node [color=\"#ffffff\"]"));
+        assert!(dot.contains("    edge [color=\"#ff0000\"]"));
+        // the node's own color comes after the defaults and wins
+        assert!(dot.find("node [").unwrap() < dot.find("#00ff00").unwrap());
+    }
+
+    #[test]
+    fn test_html_label_to_dot() {
+        use crate::style::html::{Cell, Row, Table, Text};
+
+        let label = Table::new().row(Row::new().cell(Cell::new(Text::new().bold("A")).port("p")));
+        let node = Node {
+            id: "A".into(),
+            label: "A".into(),
+            attributes: vec![CommonAttr::HtmlLabel(label.into()).into()],
+        };
+        let mut edge = Edge::new("e", "A", "B");
+        edge.set_attr(CommonAttr::HtmlLabel(Text::new().italic("x & y").into()));
+        let graph = RustGraph::from_parts("G".into(), vec![node], vec![edge]);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("label=<<TABLE><TR><TD PORT=\"p\"><B>A</B></TD></TR></TABLE>>"));
+        assert!(!dot.contains("label=\"A\""));
+        assert!(dot.contains("label=<<I>x &amp; y</I>>"));
+    }
+
+    #[test]
+    fn test_record_ports() {
+        use crate::style::record::RecordLabel;
+        use crate::style::CompassPoint;
+
+        let mut node_a = Node::new("A", "A");
+        node_a.set_attr(CommonAttr::from(RecordLabel::new().port_field("out", "out").field("x")));
+        let node_b = Node::new("B", "B");
+        let edge = Edge::new("A->B", "A", "B");
+        let mut graph = RustGraph::from_parts("G".into(), vec![node_a, node_b], vec![edge]);
+
+        graph.add_edge_attr("A->B".into(), EdgeAttr::TailPort(Port::named("out").at(CompassPoint::E))).unwrap();
+        graph.add_edge_attr("A->B".into(), EdgeAttr::HeadPort(Port::compass(CompassPoint::W))).unwrap();
+        let err = graph.add_edge_attr("A->B".into(), EdgeAttr::HeadPort(Port::named("in")));
+        assert!(matches!(err, Err(Error::PortNotFound { node, .. }) if node == "B"));
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("label=\"<out> out | x\""));
+        assert!(dot.contains("tailport=\"out:e\""));
+        assert!(dot.contains("headport=\"w\""));
+    }
+
+    #[test]
+    fn test_clusters_to_dot() {
+        use crate::style::{ClusterAttr, ClusterStyle};
+
+        let nodes = ["A", "B", "C"].map(|id| Node::new(id, id));
+        let mut graph = RustGraph::from_parts(
+            "G".into(),
+            nodes.to_vec(),
+            vec![Edge::new("A->B", "A", "B"), Edge::new("C->C", "C", "C")],
+        );
+        let mut inner = RustGraph::from_parts(
+            "inner".into(),
+            vec![nodes[1].clone(), nodes[2].clone()],
+            vec![Edge::new("B->C", "B", "C")],
+        );
+        inner.add_cluster_attr(CommonAttr::Label("Inner".into()));
+        let mut outer = RustGraph::from_parts(
+            "outer".into(),
+            nodes.to_vec(),
+            vec![Edge::new("A->B", "A", "B"), Edge::new("B->C", "B", "C")],
+        );
+        outer.add_cluster_attr(ClusterAttr::Style(ClusterStyle::Filled));
+        outer.add_cluster(inner).unwrap();
+        graph.add_cluster(outer).unwrap();
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("    subgraph cluster_outer {\n"));
+        assert!(dot.contains("        subgraph cluster_inner {\n"));
+        assert!(dot.contains("            label=\"Inner\"\n"));
+        assert!(dot.contains("\"C\" -> \"C\""));
+        // edges the root graph already has are not repeated in the cluster
+        assert_eq!(dot.matches("\"A\" -> \"B\"").count(), 1);
+        // nor are edges shared by nested clusters, which the root doesn't have
+        assert_eq!(dot.matches("\"B\" -> \"C\"").count(), 1);
+    }
+
     #[test]
     fn test_empty_graph() {
         let graph = RustGraph::from_parts("G".into(), vec![], vec![]);
@@ -686,5 +1054,38 @@ mod tests {
         assert!(dot.contains("\"A\" -> \"B\""));
         assert!(dot.contains("\"B\" -> \"C\""));
     }
-}
 
+    #[test]
+    fn test_graph_kind_and_name() {
+        let mut graph = RustGraph::with_kind("my graph".into(), GraphKind::StrictUndirected);
+        graph.add_node(Node::new("A", "A"));
+        graph.add_node(Node::new("B", "B"));
+        graph.add_edge(<Edge as CompatEdge>::new("A_B", "A", "B"));
+        assert_eq!(graph.kind(), GraphKind::StrictUndirected);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("strict graph \"my graph\" {"));
+        assert!(dot.contains("\"A\" -- \"B\""));
+        assert!(!dot.contains("->"));
+
+        graph.set_kind(GraphKind::Directed);
+        assert!(graph.to_dot().starts_with("digraph \"my graph\" {"));
+        assert_eq!(dot_id("G_1"), "G_1");
+        assert_eq!(dot_id("1G"), "\"1G\"");
+        assert_eq!(dot_id("Node"), "\"Node\"");
+        assert_eq!(dot_id("subgraph"), "\"subgraph\"");
+        assert_eq!(dot_id("nodes"), "nodes");
+        assert_eq!(dot_id(r#"a\"b"#), r#""a\\\"b""#);
+    }
+
+    #[test]
+    fn test_parse_undirected_edge() {
+        let edge: Edge = "\"A\" -- \"B\" [id=\"A_B\"]".parse().unwrap();
+        assert_eq!(edge.source, "\"A\"");
+        assert_eq!(edge.dest, "\"B\"");
+
+        let edge: Edge = "\"A\" -- \"B\" [id=\"A_B\", label=\"x->y\"]".parse().unwrap();
+        assert_eq!(edge.source, "\"A\"");
+        assert_eq!(edge.dest, "\"B\"");
+    }
+}
@@ -41,7 +41,7 @@ impl WasmAttribute {
 // Conversion impls to your enums using existing TryFrom<(&str, &str)>
 
 impl TryFrom<WasmAttribute> for EdgeAttribute {
-    type Error = &'static str;
+    type Error = crate::Error;
 
     fn try_from(attr: WasmAttribute) -> Result<Self, Self::Error> {
         EdgeAttribute::try_from((attr.key.as_str(), attr.value.as_str()))
@@ -49,7 +49,7 @@ impl TryFrom<WasmAttribute> for EdgeAttribute {
 }
 
 impl TryFrom<WasmAttribute> for NodeAttribute {
-    type Error = &'static str;
+    type Error = crate::Error;
 
     fn try_from(attr: WasmAttribute) -> Result<Self, Self::Error> {
         NodeAttribute::try_from((attr.key.as_str(), attr.value.as_str()))
@@ -57,7 +57,7 @@ impl TryFrom<WasmAttribute> for NodeAttribute {
 }
 
 impl TryFrom<WasmAttribute> for GraphAttr {
-    type Error = &'static str;
+    type Error = crate::Error;
 
     fn try_from(attr: WasmAttribute) -> Result<Self, Self::Error> {
         GraphAttr::try_from((attr.key.as_str(), attr.value.as_str()))
@@ -92,22 +92,18 @@ impl WasmRustGraph {
         self.inner.add_edge(uuid, label, source_uuid, dest_uuid);
     }
 
-    /// Add an attribute to a node by UUID string.
+    /// Add an attribute to a node by id, throws if the node doesn't exist or the attribute is invalid.
     #[wasm_bindgen]
-    pub fn add_node_attr(&mut self, node_id: &str, attr_json: &JsValue) {
-        let uuid = Uuid::parse_str(node_id).expect("Invalid UUID");
-        let attr: NodeAttribute = serde_wasm_bindgen::from_value(attr_json.clone())
-            .expect("Failed to parse NodeAttribute from JS");
-        self.inner.add_node_attr(uuid, attr);
+    pub fn add_node_attr(&mut self, node_id: &str, attr_json: &JsValue) -> Result<(), JsValue> {
+        let attr: NodeAttribute = serde_wasm_bindgen::from_value(attr_json.clone()).map_err(to_js_error)?;
+        self.inner.add_node_attr(node_id.to_string(), attr).map_err(to_js_error)
     }
 
-    /// Add an attribute to an edge by UUID string.
+    /// Add an attribute to an edge by id, throws if the edge doesn't exist or the attribute is invalid.
     #[wasm_bindgen]
-    pub fn add_edge_attr(&mut self, edge_id: &str, attr_json: &JsValue) {
-        let uuid = Uuid::parse_str(edge_id).expect("Invalid UUID");
-        let attr: EdgeAttribute = serde_wasm_bindgen::from_value(attr_json.clone())
-            .expect("Failed to parse EdgeAttribute from JS");
-        self.inner.add_edge_attr(uuid, attr);
+    pub fn add_edge_attr(&mut self, edge_id: &str, attr_json: &JsValue) -> Result<(), JsValue> {
+        let attr: EdgeAttribute = serde_wasm_bindgen::from_value(attr_json.clone()).map_err(to_js_error)?;
+        self.inner.add_edge_attr(edge_id.to_string(), attr).map_err(to_js_error)
     }
 
     /// Add a graph-level attribute.
@@ -119,3 +115,7 @@ impl WasmRustGraph {
     }
 }
 
+/// turns a Rust error into the exception thrown on the JS side
+fn to_js_error<E: std::fmt::Display>(err: E) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
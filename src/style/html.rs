@@ -0,0 +1,477 @@
+//! Builder for graphviz HTML-like labels.
+//!
+//! HTML-like labels are the way to put tables, ports and mixed fonts into a node. They are set
+//! with [`CommonAttr::HtmlLabel`](crate::style::CommonAttr::HtmlLabel), which cgraph stores as an
+//! HTML string and [`RustGraph::to_dot`](crate::rgraph::RustGraph::to_dot) writes as `label=<...>`.
+//! All text and attribute values are escaped.
+//!
+//! ```rust
+//! use wrappedviz::style::html::{Cell, HtmlLabel, Row, Table, Text};
+//!
+//! let label: HtmlLabel = Table::new()
+//!     .border(0)
+//!     .cell_border(1)
+//!     .row(Row::new().cell(Cell::new(Text::new().bold("users"))))
+//!     .row(Row::new().cell(Cell::new("id").port("id")).cell(Cell::new("a < b")))
+//!     .into();
+//! assert_eq!(
+//!     label.to_string(),
+//!     "<TABLE BORDER=\"0\" CELLBORDER=\"1\"><TR><TD><B>users</B></TD></TR>\
+//!      <TR><TD PORT=\"id\">id</TD><TD>a &lt; b</TD></TR></TABLE>"
+//! );
+//! ```
+//!
+//! The supported elements are described at <https://graphviz.org/doc/info/shapes.html#html>.
+
+use crate::style::color::Color;
+use std::fmt;
+
+/// Escapes `&`, `<`, `>` and `"` so `text` can be used in HTML-like label text and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// An HTML-like label, written without the enclosing `<` `>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HtmlLabel {
+    /// formatted text
+    Text(Text),
+    /// a table, the only way to get ports and cells
+    Table(Table),
+    /// markup that is used as is, e.g. read back from a DOT file
+    Raw(String),
+}
+
+impl HtmlLabel {
+    /// Uses `markup` as is, without escaping or checking it.
+    pub fn raw<S: Into<String>>(markup: S) -> Self {
+        HtmlLabel::Raw(markup.into())
+    }
+}
+
+impl fmt::Display for HtmlLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HtmlLabel::Text(text) => write!(f, "{}", text),
+            HtmlLabel::Table(table) => write!(f, "{}", table),
+            HtmlLabel::Raw(markup) => write!(f, "{}", markup),
+        }
+    }
+}
+
+impl From<Text> for HtmlLabel {
+    fn from(text: Text) -> Self {
+        HtmlLabel::Text(text)
+    }
+}
+
+impl From<Table> for HtmlLabel {
+    fn from(table: Table) -> Self {
+        HtmlLabel::Table(table)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for HtmlLabel {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for HtmlLabel {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        use serde::Deserialize;
+        String::deserialize(deserializer).map(HtmlLabel::Raw)
+    }
+}
+
+/// Horizontal alignment of a cell's content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl fmt::Display for Align {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Align::Left => write!(f, "LEFT"),
+            Align::Center => write!(f, "CENTER"),
+            Align::Right => write!(f, "RIGHT"),
+        }
+    }
+}
+
+/// `<FONT>` settings for a run of text.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Font {
+    attrs: Vec<(&'static str, String)>,
+}
+
+impl Font {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the font name, e.g. `Helvetica`
+    pub fn face(mut self, face: &str) -> Self {
+        self.attrs.push(("FACE", face.to_string()));
+        self
+    }
+
+    /// the font size in points
+    pub fn size(mut self, size: f32) -> Self {
+        self.attrs.push(("POINT-SIZE", size.to_string()));
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.attrs.push(("COLOR", color.to_string()));
+        self
+    }
+}
+
+/// a piece of formatted text
+#[derive(Debug, Clone, PartialEq)]
+enum TextItem {
+    Plain(String),
+    Break,
+    Tagged(&'static str, Text),
+    Font(Font, Text),
+}
+
+/// Formatted text: plain strings, line breaks, `<B>`, `<I>`, `<U>` and `<FONT>`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Text {
+    items: Vec<TextItem>,
+}
+
+impl Text {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// appends plain text
+    pub fn plain(mut self, text: &str) -> Self {
+        self.items.push(TextItem::Plain(text.to_string()));
+        self
+    }
+
+    /// appends a line break, `<BR/>`
+    pub fn br(mut self) -> Self {
+        self.items.push(TextItem::Break);
+        self
+    }
+
+    /// appends bold text, `<B>`
+    pub fn bold<T: Into<Text>>(mut self, text: T) -> Self {
+        self.items.push(TextItem::Tagged("B", text.into()));
+        self
+    }
+
+    /// appends italic text, `<I>`
+    pub fn italic<T: Into<Text>>(mut self, text: T) -> Self {
+        self.items.push(TextItem::Tagged("I", text.into()));
+        self
+    }
+
+    /// appends underlined text, `<U>`
+    pub fn underline<T: Into<Text>>(mut self, text: T) -> Self {
+        self.items.push(TextItem::Tagged("U", text.into()));
+        self
+    }
+
+    /// appends text in another font, `<FONT>`
+    pub fn font<T: Into<Text>>(mut self, font: Font, text: T) -> Self {
+        self.items.push(TextItem::Font(font, text.into()));
+        self
+    }
+}
+
+impl From<&str> for Text {
+    fn from(text: &str) -> Self {
+        Text::new().plain(text)
+    }
+}
+
+impl From<String> for Text {
+    fn from(text: String) -> Self {
+        Text { items: vec![TextItem::Plain(text)] }
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            match item {
+                TextItem::Plain(text) => write!(f, "{}", escape(text))?,
+                TextItem::Break => write!(f, "<BR/>")?,
+                TextItem::Tagged(tag, text) => write!(f, "<{tag}>{text}</{tag}>")?,
+                TextItem::Font(font, text) => {
+                    write!(f, "<FONT")?;
+                    write_attrs(f, &font.attrs)?;
+                    write!(f, ">{}</FONT>", text)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `<TABLE>` made of [`Row`]s.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Table {
+    attrs: Vec<(&'static str, String)>,
+    rows: Vec<Row>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// width of the table's outer border, graphviz draws `1` by default
+    pub fn border(mut self, border: u32) -> Self {
+        self.attrs.push(("BORDER", border.to_string()));
+        self
+    }
+
+    /// width of the border around every cell
+    pub fn cell_border(mut self, border: u32) -> Self {
+        self.attrs.push(("CELLBORDER", border.to_string()));
+        self
+    }
+
+    /// space between cells
+    pub fn cell_spacing(mut self, spacing: u32) -> Self {
+        self.attrs.push(("CELLSPACING", spacing.to_string()));
+        self
+    }
+
+    /// space between a cell's border and its content
+    pub fn cell_padding(mut self, padding: u32) -> Self {
+        self.attrs.push(("CELLPADDING", padding.to_string()));
+        self
+    }
+
+    pub fn bgcolor(mut self, color: Color) -> Self {
+        self.attrs.push(("BGCOLOR", color.to_string()));
+        self
+    }
+
+    /// the border color
+    pub fn color(mut self, color: Color) -> Self {
+        self.attrs.push(("COLOR", color.to_string()));
+        self
+    }
+
+    /// names the whole table as a port edges can attach to
+    pub fn port(mut self, port: &str) -> Self {
+        self.attrs.push(("PORT", port.to_string()));
+        self
+    }
+
+    pub fn row(mut self, row: Row) -> Self {
+        self.rows.push(row);
+        self
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<TABLE")?;
+        write_attrs(f, &self.attrs)?;
+        write!(f, ">")?;
+        for row in &self.rows {
+            write!(f, "{}", row)?;
+        }
+        write!(f, "</TABLE>")
+    }
+}
+
+/// A `<TR>` of [`Cell`]s.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Row {
+    cells: Vec<Cell>,
+}
+
+impl Row {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cell(mut self, cell: Cell) -> Self {
+        self.cells.push(cell);
+        self
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<TR>")?;
+        for cell in &self.cells {
+            write!(f, "{}", cell)?;
+        }
+        write!(f, "</TR>")
+    }
+}
+
+/// what a [`Cell`] holds
+#[derive(Debug, Clone, PartialEq)]
+enum CellContent {
+    Text(Text),
+    Table(Table),
+    Image { src: String, scale: Option<String> },
+}
+
+/// A `<TD>` holding text, a nested table or an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    attrs: Vec<(&'static str, String)>,
+    content: CellContent,
+}
+
+impl Cell {
+    /// a cell holding text
+    pub fn new<T: Into<Text>>(text: T) -> Self {
+        Self::with_content(CellContent::Text(text.into()))
+    }
+
+    /// a cell holding a nested table
+    pub fn table(table: Table) -> Self {
+        Self::with_content(CellContent::Table(table))
+    }
+
+    /// a cell holding the image file `src`, `<IMG SRC="..."/>`
+    pub fn image(src: &str) -> Self {
+        Self::with_content(CellContent::Image { src: src.to_string(), scale: None })
+    }
+
+    fn with_content(content: CellContent) -> Self {
+        Self { attrs: Vec::new(), content }
+    }
+
+    /// how an image is scaled to the cell: `false`, `true`, `width`, `height` or `both`
+    ///
+    /// has no effect on cells without an image
+    pub fn scale(mut self, scale: &str) -> Self {
+        if let CellContent::Image { scale: image_scale, .. } = &mut self.content {
+            *image_scale = Some(scale.to_string());
+        }
+        self
+    }
+
+    /// names the cell as a port edges can attach to, e.g. `node:port`
+    pub fn port(mut self, port: &str) -> Self {
+        self.attrs.push(("PORT", port.to_string()));
+        self
+    }
+
+    pub fn colspan(mut self, span: u32) -> Self {
+        self.attrs.push(("COLSPAN", span.to_string()));
+        self
+    }
+
+    pub fn rowspan(mut self, span: u32) -> Self {
+        self.attrs.push(("ROWSPAN", span.to_string()));
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.attrs.push(("ALIGN", align.to_string()));
+        self
+    }
+
+    pub fn bgcolor(mut self, color: Color) -> Self {
+        self.attrs.push(("BGCOLOR", color.to_string()));
+        self
+    }
+
+    /// width of the cell's border, overriding the table's `CELLBORDER`
+    pub fn border(mut self, border: u32) -> Self {
+        self.attrs.push(("BORDER", border.to_string()));
+        self
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<TD")?;
+        write_attrs(f, &self.attrs)?;
+        write!(f, ">")?;
+        match &self.content {
+            CellContent::Text(text) => write!(f, "{}", text)?,
+            CellContent::Table(table) => write!(f, "{}", table)?,
+            CellContent::Image { src, scale } => {
+                write!(f, "<IMG SRC=\"{}\"", escape(src))?;
+                if let Some(scale) = scale {
+                    write!(f, " SCALE=\"{}\"", escape(scale))?;
+                }
+                write!(f, "/>")?;
+            }
+        }
+        write!(f, "</TD>")
+    }
+}
+
+fn write_attrs(f: &mut fmt::Formatter<'_>, attrs: &[(&'static str, String)]) -> fmt::Result {
+    for (name, value) in attrs {
+        write!(f, " {}=\"{}\"", name, escape(value))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a < b && \"c\" > d"), "a &lt; b &amp;&amp; &quot;c&quot; &gt; d");
+    }
+
+    #[test]
+    fn test_text_formatting() {
+        let text = Text::new()
+            .plain("name: ")
+            .bold(Text::new().italic("x<y>"))
+            .br()
+            .font(Font::new().face("Courier").size(10.0), "mono");
+        assert_eq!(
+            HtmlLabel::from(text).to_string(),
+            "name: <B><I>x&lt;y&gt;</I></B><BR/><FONT FACE=\"Courier\" POINT-SIZE=\"10\">mono</FONT>"
+        );
+    }
+
+    #[test]
+    fn test_table_cells() {
+        let table = Table::new()
+            .bgcolor("#ffffff".parse().unwrap())
+            .row(
+                Row::new()
+                    .cell(Cell::image("logo.png").scale("true").rowspan(2))
+                    .cell(Cell::new("a\"b").port("p\"1").align(Align::Left)),
+            )
+            .row(Row::new().cell(Cell::table(Table::new().border(0))));
+        assert_eq!(
+            table.to_string(),
+            "<TABLE BGCOLOR=\"#ffffff\"><TR><TD ROWSPAN=\"2\"><IMG SRC=\"logo.png\" SCALE=\"true\"/></TD>\
+             <TD PORT=\"p&quot;1\" ALIGN=\"LEFT\">a&quot;b</TD></TR>\
+             <TR><TD><TABLE BORDER=\"0\"></TABLE></TD></TR></TABLE>"
+        );
+    }
+}
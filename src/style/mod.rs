@@ -2,11 +2,16 @@
 pub mod color;
 /// Defines supported shapes for graphviz.
 pub mod shape;
+pub mod html;
+pub mod port;
+pub use port::{label_ports, CompassPoint, Port};
+pub mod record;
 pub use shape::*;
 #[cfg(feature = "serde")]
 pub mod serialize;
 use crate::style::shape::{ArrowType, NodeShape};
 use color::Color;
+use crate::Error;
 use std::ffi::CString;
 use std::fmt;
 use std::str::FromStr;
@@ -22,6 +27,10 @@ fn parse_key_value<'r>(s: &'r str) -> Option<(&'r str, &'r str)> {
     Some((key, value))
 }
 
+fn invalid_attr(reason: &str, key: &str, value: &str) -> Error {
+    Error::ParseFailed(format!("{}: {}=\"{}\"", reason, key, value))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(
     feature = "serde",
@@ -118,6 +127,10 @@ impl Attribute for NodeAttribute {
         }
     }
 
+    fn is_html(&self) -> bool {
+        matches!(self, NodeAttribute::Common(c) if c.is_html())
+    }
+
     fn attr_name(&self) -> &str {
         match self {
             NodeAttribute::Common(c) => c.attr_name(),
@@ -127,7 +140,7 @@ impl Attribute for NodeAttribute {
 }
 
 impl TryFrom<(&str, &str)> for NodeAttribute {
-    type Error = &'static str;
+    type Error = Error;
 
     fn try_from((key, value): (&str, &str)) -> Result<Self, Self::Error> {
         if let Ok(common) = CommonAttr::try_from((key, value)) {
@@ -135,7 +148,7 @@ impl TryFrom<(&str, &str)> for NodeAttribute {
         } else if let Ok(node_attr) = NodeAttr::try_from((key, value)) {
             Ok(NodeAttribute::NodeAttr(node_attr))
         } else {
-            Err("Unknown NodeAttribute key or invalid value")
+            Err(invalid_attr("Unknown NodeAttribute key or invalid value", key, value))
         }
     }
 }
@@ -165,6 +178,10 @@ impl Attribute for EdgeAttribute {
         }
     }
 
+    fn is_html(&self) -> bool {
+        matches!(self, EdgeAttribute::Common(c) if c.is_html())
+    }
+
     fn attr_name(&self) -> &str {
         match self {
             EdgeAttribute::Common(c) => c.attr_name(),
@@ -174,7 +191,7 @@ impl Attribute for EdgeAttribute {
 }
 
 impl TryFrom<(&str, &str)> for EdgeAttribute {
-    type Error = &'static str;
+    type Error = Error;
 
     fn try_from((key, value): (&str, &str)) -> Result<Self, Self::Error> {
         if let Ok(common) = CommonAttr::try_from((key, value)) {
@@ -182,7 +199,7 @@ impl TryFrom<(&str, &str)> for EdgeAttribute {
         } else if let Ok(edge_attr) = EdgeAttr::try_from((key, value)) {
             Ok(EdgeAttribute::EdgeAttr(edge_attr))
         } else {
-            Err("Unknown EdgeAttribute key or invalid value")
+            Err(invalid_attr("Unknown EdgeAttribute key or invalid value", key, value))
         }
     }
 }
@@ -198,7 +215,7 @@ impl fmt::Display for EdgeAttribute {
 }
 
 impl FromStr for NodeAttribute {
-    type Err = &'static str;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Ok(common) = s.parse::<CommonAttr>() {
@@ -206,13 +223,13 @@ impl FromStr for NodeAttribute {
         } else if let Ok(node_attr) = s.parse::<NodeAttr>() {
             Ok(NodeAttribute::NodeAttr(node_attr))
         } else {
-            Err("Failed to parse NodeAttribute")
+            Err(Error::ParseFailed(format!("Failed to parse NodeAttribute '{}'", s)))
         }
     }
 }
 
 impl FromStr for EdgeAttribute {
-    type Err = &'static str;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Ok(common) = s.parse::<CommonAttr>() {
@@ -220,7 +237,7 @@ impl FromStr for EdgeAttribute {
         } else if let Ok(edge_attr) = s.parse::<EdgeAttr>() {
             Ok(EdgeAttribute::EdgeAttr(edge_attr))
         } else {
-            Err("Failed to parse EdgeAttribute")
+            Err(Error::ParseFailed(format!("Failed to parse EdgeAttribute '{}'", s)))
         }
     }
 }
@@ -233,6 +250,10 @@ pub trait Attribute {
     fn default(&self) -> &'static str {
         ""
     }
+    /// returns `true` if the value is an HTML-like label that graphviz has to parse as markup
+    fn is_html(&self) -> bool {
+        false
+    }
     /// returns a string representation of the attributes variant, such as is used to convert to string types.
     fn attr_name(&self) -> &str;
 }
@@ -445,9 +466,15 @@ impl NodeAttr {
 }
 
 impl TryFrom<(&str, &str)> for NodeAttr {
-    type Error = &'static str;
+    type Error = Error;
 
     fn try_from((key, value): (&str, &str)) -> Result<Self, Self::Error> {
+        NodeAttr::from_pair(key, value).map_err(|reason| invalid_attr(reason, key, value))
+    }
+}
+
+impl NodeAttr {
+    fn from_pair(key: &str, value: &str) -> Result<Self, &'static str> {
         match key {
             "shape"      => value.parse().map(NodeAttr::Shape).map_err(|_| "Invalid shape"),
             "style"      => value.parse().map(NodeAttr::Style).map_err(|_| "Invalid style"),
@@ -469,13 +496,13 @@ impl TryFrom<(&str, &str)> for NodeAttr {
 }
 
 impl FromStr for NodeAttr {
-    type Err = &'static str;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some((key, value)) = parse_key_value(s) {
             NodeAttr::try_from((key, value))
         } else {
-            Err("Invalid NodeAttr format: expected key=\"value\"")
+            Err(Error::ParseFailed(format!("Invalid NodeAttr format, expected key=\"value\": '{}'", s)))
         }
     }
 }
@@ -505,6 +532,10 @@ pub enum EdgeAttr {
     Constraint(bool),
     Style(EdgeStyle),
     Color(Color),
+    /// where the edge leaves its tail node
+    TailPort(Port),
+    /// where the edge enters its head node
+    HeadPort(Port),
 }
 
 impl Attribute for EdgeAttr {
@@ -522,6 +553,8 @@ impl Attribute for EdgeAttr {
             MinLen(_) => "minlen",
             LabelDistance(_) => "labeldistance",
             LabelAngle(_) => "labelangle",
+            TailPort(_) => "tailport",
+            HeadPort(_) => "headport",
             Constraint(_) => "constraint",
             Style(_) => "style",
             Color(_) => "color",
@@ -573,14 +606,28 @@ impl EdgeAttr {
                 CString::new("style").unwrap(),
                 CString::new(v.to_string()).unwrap(),
             ),
+            TailPort(v) => (
+                CString::new("tailport").unwrap(),
+                CString::new(v.to_string()).unwrap(),
+            ),
+            HeadPort(v) => (
+                CString::new("headport").unwrap(),
+                CString::new(v.to_string()).unwrap(),
+            ),
         }
     }
 }
 
 impl TryFrom<(&str, &str)> for EdgeAttr {
-    type Error = &'static str;
+    type Error = Error;
 
     fn try_from((key, value): (&str, &str)) -> Result<Self, Self::Error> {
+        EdgeAttr::from_pair(key, value).map_err(|reason| invalid_attr(reason, key, value))
+    }
+}
+
+impl EdgeAttr {
+    fn from_pair(key: &str, value: &str) -> Result<Self, &'static str> {
         match key {
             "arrowhead"     => value.parse().map(EdgeAttr::ArrowHead).map_err(|_| "Invalid arrowhead"),
             "arrowtail"     => value.parse().map(EdgeAttr::ArrowTail).map_err(|_| "Invalid arrowtail"),
@@ -596,19 +643,21 @@ impl TryFrom<(&str, &str)> for EdgeAttr {
             },
             "style"         => value.parse().map(EdgeAttr::Style).map_err(|_| "Invalid style"),
             "color"         => value.parse().map(EdgeAttr::Color).map_err(|_| "Invalid color"),
+            "tailport"      => value.parse().map(EdgeAttr::TailPort).map_err(|_| "Invalid tailport"),
+            "headport"      => value.parse().map(EdgeAttr::HeadPort).map_err(|_| "Invalid headport"),
             _               => Err("Unknown EdgeAttr key"),
         }
     }
 }
 
 impl FromStr for EdgeAttr {
-    type Err = &'static str;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some((key, value)) = parse_key_value(s) {
             EdgeAttr::try_from((key, value))
         } else {
-            Err("Invalid EdgeAttr format: expected key=\"value\"")
+            Err(Error::ParseFailed(format!("Invalid EdgeAttr format, expected key=\"value\": '{}'", s)))
         }
     }
 }
@@ -633,6 +682,8 @@ impl fmt::Display for EdgeAttr {
             Constraint(v) => write!(f, "constraint=\"{}\"", v),
             Style(v) => write!(f, "style=\"{}\"", v),
             Color(v) => write!(f, "color=\"{}\"", v),
+            TailPort(v) => write!(f, "tailport=\"{}\"", v),
+            HeadPort(v) => write!(f, "headport=\"{}\"", v),
         }
     }
 }
@@ -729,9 +780,15 @@ impl GraphAttr {
 }
 
 impl TryFrom<(&str, &str)> for GraphAttr {
-    type Error = &'static str;
+    type Error = Error;
 
     fn try_from((key, value): (&str, &str)) -> Result<Self, Self::Error> {
+        GraphAttr::from_pair(key, value).map_err(|reason| invalid_attr(reason, key, value))
+    }
+}
+
+impl GraphAttr {
+    fn from_pair(key: &str, value: &str) -> Result<Self, &'static str> {
         match key {
             "layout"   => value.parse().map(GraphAttr::Layout).map_err(|_| "Invalid layout"),
             "rankdir"  => value.parse().map(GraphAttr::RankDir).map_err(|_| "Invalid rankdir"),
@@ -758,13 +815,13 @@ impl TryFrom<(&str, &str)> for GraphAttr {
 }
 
 impl FromStr for GraphAttr {
-    type Err = &'static str;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some((key, value)) = parse_key_value(s) {
             GraphAttr::try_from((key, value))
         } else {
-            Err("Invalid GraphAttr format: expected key=\"value\"")
+            Err(Error::ParseFailed(format!("Invalid GraphAttr format, expected key=\"value\": '{}'", s)))
         }
     }
 }
@@ -802,6 +859,8 @@ impl fmt::Display for GraphAttr {
 )]
 pub enum CommonAttr {
     Label(String),
+    /// an HTML-like label built with [`html`], written as `label=<...>`
+    HtmlLabel(html::HtmlLabel),
     FontSize(f32),
     FontName(String),
     Id(String),
@@ -817,10 +876,15 @@ impl Attribute for CommonAttr {
         self.to_cstrings()
     }
 
+    fn is_html(&self) -> bool {
+        matches!(self, CommonAttr::HtmlLabel(_))
+    }
+
     fn attr_name(&self) -> &str {
         use CommonAttr::*;
         match self {
             Label(_)     => "label",
+            HtmlLabel(_) => "label",
             FontSize(_)  => "fontsize",
             URL(_)       => "URL",
             Class(_)     => "class",
@@ -840,6 +904,10 @@ impl CommonAttr {
                 CString::new("label").unwrap(),
                 CString::new(v.as_str()).unwrap(),
             ),
+            HtmlLabel(v) => (
+                CString::new("label").unwrap(),
+                CString::new(v.to_string()).unwrap(),
+            ),
             FontSize(v) => (
                 CString::new("fontsize").unwrap(),
                 CString::new(format!("{}", v)).unwrap(),
@@ -883,6 +951,7 @@ impl fmt::Display for CommonAttr {
         use CommonAttr::*;
         match self {
             Label(v) => write!(f, "label=\"{}\"", v),
+            HtmlLabel(v) => write!(f, "label=<{}>", v),
             FontSize(v) => write!(f, "fontsize=\"{}\"", v),
             FontName(v) => write!(f, "fontname=\"{}\"", v),
             Id(v) => write!(f, "id=\"{}\"", v),
@@ -895,9 +964,15 @@ impl fmt::Display for CommonAttr {
 }
 
 impl TryFrom<(&str, &str)> for CommonAttr {
-    type Error = &'static str;
+    type Error = Error;
 
     fn try_from((key, value): (&str, &str)) -> Result<Self, Self::Error> {
+        CommonAttr::from_pair(key, value).map_err(|reason| invalid_attr(reason, key, value))
+    }
+}
+
+impl CommonAttr {
+    fn from_pair(key: &str, value: &str) -> Result<Self, &'static str> {
         match key {
             "label"     => Ok(CommonAttr::Label(value.to_string())),
             "fontsize"  => value.parse().map(CommonAttr::FontSize).map_err(|_| "Invalid fontsize"),
@@ -912,13 +987,20 @@ impl TryFrom<(&str, &str)> for CommonAttr {
 }
 
 impl FromStr for CommonAttr {
-    type Err = &'static str;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some((key, value)) = parse_key_value(s) {
+            // `label=<...>` is markup, `label="<...>"` is plain text that happens to look like it
+            let markup = s
+                .split_once('=')
+                .and_then(|(_, raw)| raw.trim().strip_prefix('<')?.strip_suffix('>'));
+            if let ("label", Some(markup)) = (key, markup) {
+                return Ok(CommonAttr::HtmlLabel(html::HtmlLabel::raw(markup)));
+            }
             CommonAttr::try_from((key, value))
         } else {
-            Err("Invalid CommonAttr format: expected key=\"value\"")
+            Err(Error::ParseFailed(format!("Invalid CommonAttr format, expected key=\"value\": '{}'", s)))
         }
     }
 }
@@ -941,6 +1023,23 @@ mod tests {
         assert_eq!(original, parsed);
     }
 
+    #[test]
+    fn test_html_label_attr() {
+        let label = html::Text::new().bold("a & b");
+        let attr = CommonAttr::HtmlLabel(label.into());
+        assert!(attr.is_html());
+        assert!(NodeAttribute::from(attr.clone()).is_html());
+        assert!(!CommonAttr::Label("<b>".into()).is_html());
+        assert_eq!(attr.attr_name(), "label");
+        assert_eq!(attr.to_string(), "label=<<B>a &amp; b</B>>");
+        assert_eq!(attr.to_cstrings().1.to_str().unwrap(), "<B>a &amp; b</B>");
+
+        let parsed = CommonAttr::from_str(&attr.to_string()).unwrap();
+        assert_eq!(parsed, CommonAttr::HtmlLabel(html::HtmlLabel::raw("<B>a &amp; b</B>")));
+        let plain = CommonAttr::from_str("label=\"<b>\"").unwrap();
+        assert_eq!(plain, CommonAttr::Label("<b>".into()));
+    }
+
     #[test]
     fn test_node_attr_round_trip() {
         let original = NodeAttr::Shape(NodeShape::Box);
@@ -1092,6 +1191,18 @@ mod tests {
         assert_eq!(attr.to_string(), "peripheries=\"2\"");
     }
 
+    #[test]
+    fn test_cluster_attribute_name_and_pairs() {
+        let attr: ClusterAttribute = CommonAttr::Label("MyCluster".into()).into();
+        assert_eq!(attr.attr_name(), "label");
+        let attr: ClusterAttribute = ClusterAttr::Peripheries(2).into();
+        assert_eq!(attr.attr_name(), "peripheries");
+
+        let attr = ClusterAttribute::try_from(("style", "filled")).unwrap();
+        assert_eq!(attr, ClusterAttribute::ClusterAttr(ClusterAttr::Style(ClusterStyle::Filled)));
+        assert!(ClusterAttribute::try_from(("shape", "box")).is_err());
+    }
+
     #[test]
     fn test_fromstr_cluster_attribute() {
         let attr = ClusterAttribute::from_str("label=\"MyCluster\"").unwrap();
@@ -1179,13 +1290,13 @@ impl fmt::Display for ClusterAttr {
 }
 
 impl FromStr for ClusterAttr {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         // parse string of form key="value" or key=value
         let parts: Vec<&str> = s.splitn(2, '=').collect();
         if parts.len() != 2 {
-            return Err(format!("Invalid cluster attribute: '{}'", s));
+            return Err(Error::ParseFailed(format!("Invalid cluster attribute: '{}'", s)));
         }
 
         let key = parts[0].trim().to_lowercase();
@@ -1194,25 +1305,32 @@ impl FromStr for ClusterAttr {
         if value.starts_with('"') && value.ends_with('"') {
             value = &value[1..value.len()-1];
         }
+        ClusterAttr::try_from((key.as_str(), value))
+    }
+}
 
-        match key.as_str() {
+impl TryFrom<(&str, &str)> for ClusterAttr {
+    type Error = Error;
+
+    fn try_from((key, value): (&str, &str)) -> Result<Self, Self::Error> {
+        match key {
             "style" => {
-                let parsed = ClusterStyle::from_str(value).map_err(|_| format!("Invalid style: {}", value))?;
+                let parsed = ClusterStyle::from_str(value).map_err(|_| Error::ParseFailed(format!("Invalid style: {}", value)))?;
                 Ok(ClusterAttr::Style(parsed))
             }
             "color" => {
-                let parsed = Color::from_str(value).map_err(|_| format!("Invalid color: {}", value))?;
+                let parsed = Color::from_str(value).map_err(|_| Error::ParseFailed(format!("Invalid color: {}", value)))?;
                 Ok(ClusterAttr::Color(parsed))
             }
             "labelloc" => {
-                let parsed = LabelLoc::from_str(value).map_err(|_| format!("Invalid labelloc: {}", value))?;
+                let parsed = LabelLoc::from_str(value).map_err(|_| Error::ParseFailed(format!("Invalid labelloc: {}", value)))?;
                 Ok(ClusterAttr::LabelLoc(parsed))
             }
             "peripheries" => {
-                let parsed = value.parse::<u32>().map_err(|_| format!("Invalid peripheries: {}", value))?;
+                let parsed = value.parse::<u32>().map_err(|_| Error::ParseFailed(format!("Invalid peripheries: {}", value)))?;
                 Ok(ClusterAttr::Peripheries(parsed))
             }
-            _ => Err(format!("Unknown cluster attribute: '{}'", key)),
+            _ => Err(Error::ParseFailed(format!("Unknown cluster attribute: '{}'", key))),
         }
     }
 }
@@ -1248,8 +1366,41 @@ impl Attribute for ClusterAttribute {
         }
     }
 
+    fn is_html(&self) -> bool {
+        matches!(self, ClusterAttribute::Common(attr) if attr.is_html())
+    }
+
     fn attr_name(&self) -> &str {
-        unimplemented!();
+        match self {
+            ClusterAttribute::Common(attr) => attr.attr_name(),
+            ClusterAttribute::ClusterAttr(attr) => attr.attr_name(),
+        }
+    }
+}
+
+impl TryFrom<(&str, &str)> for ClusterAttribute {
+    type Error = Error;
+
+    fn try_from((key, value): (&str, &str)) -> Result<Self, Self::Error> {
+        if let Ok(common) = CommonAttr::try_from((key, value)) {
+            Ok(ClusterAttribute::Common(common))
+        } else if let Ok(cluster_attr) = ClusterAttr::try_from((key, value)) {
+            Ok(ClusterAttribute::ClusterAttr(cluster_attr))
+        } else {
+            Err(invalid_attr("Unknown ClusterAttribute key or invalid value", key, value))
+        }
+    }
+}
+
+impl From<CommonAttr> for ClusterAttribute {
+    fn from(attr: CommonAttr) -> ClusterAttribute {
+        ClusterAttribute::Common(attr)
+    }
+}
+
+impl From<ClusterAttr> for ClusterAttribute {
+    fn from(attr: ClusterAttr) -> ClusterAttribute {
+        ClusterAttribute::ClusterAttr(attr)
     }
 }
 
@@ -1263,7 +1414,7 @@ impl fmt::Display for ClusterAttribute {
 }
 
 impl FromStr for ClusterAttribute {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Ok(attr) = CommonAttr::from_str(s) {
@@ -1271,7 +1422,7 @@ impl FromStr for ClusterAttribute {
         } else if let Ok(attr) = ClusterAttr::from_str(s) {
             Ok(ClusterAttribute::ClusterAttr(attr))
         } else {
-            Err(format!("Invalid cluster attribute: '{}'", s))
+            Err(Error::ParseFailed(format!("Invalid cluster attribute: '{}'", s)))
         }
     }
 }
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// The side or corner of a node an edge attaches to, the compass part of a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum CompassPoint {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+    /// the center of the node or port
+    C,
+    /// `_`, any side the layout engine likes
+    Any,
+}
+
+impl fmt::Display for CompassPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CompassPoint::*;
+        let s = match self {
+            N => "n",
+            NE => "ne",
+            E => "e",
+            SE => "se",
+            S => "s",
+            SW => "sw",
+            W => "w",
+            NW => "nw",
+            C => "c",
+            Any => "_",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for CompassPoint {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use CompassPoint::*;
+        match s {
+            "n" => Ok(N),
+            "ne" => Ok(NE),
+            "e" => Ok(E),
+            "se" => Ok(SE),
+            "s" => Ok(S),
+            "sw" => Ok(SW),
+            "w" => Ok(W),
+            "nw" => Ok(NW),
+            "c" => Ok(C),
+            "_" => Ok(Any),
+            _ => Err("Unsupported compass point"),
+        }
+    }
+}
+
+/// Where an edge attaches to a node: a named record field or HTML table cell,
+/// a compass point, or both (`field:ne`). Used by `tailport` and `headport`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Port {
+    pub name: Option<String>,
+    pub compass: Option<CompassPoint>,
+}
+
+impl Port {
+    /// the record field or table cell with the port name `name`
+    pub fn named<S: Into<String>>(name: S) -> Self {
+        Self { name: Some(name.into()), compass: None }
+    }
+
+    /// a side or corner of the whole node
+    pub fn compass(compass: CompassPoint) -> Self {
+        Self { name: None, compass: Some(compass) }
+    }
+
+    /// a side or corner of the field `name`
+    pub fn at(mut self, compass: CompassPoint) -> Self {
+        self.compass = Some(compass);
+        self
+    }
+}
+
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.name, self.compass) {
+            (Some(name), Some(compass)) => write!(f, "{}:{}", name, compass),
+            (Some(name), None) => write!(f, "{}", name),
+            (None, Some(compass)) => write!(f, "{}", compass),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Port {
+    type Err = &'static str;
+
+    /// parses `name`, `compass` or `name:compass`, a lone compass point name is read as a compass point
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once(':') {
+            Some((name, compass)) if !name.is_empty() => Ok(Port::named(name).at(compass.parse()?)),
+            Some(_) => Err("Invalid port"),
+            None if s.is_empty() => Err("Invalid port"),
+            None => Ok(s
+                .parse()
+                .map(Port::compass)
+                .unwrap_or_else(|_| Port::named(s))),
+        }
+    }
+}
+
+/// Returns the port names a node label defines: the `<port>` fields of a record label,
+/// or the `PORT` attributes of an HTML-like label if `html` is set.
+pub fn label_ports(label: &str, html: bool) -> Vec<String> {
+    if html {
+        html_ports(label)
+    } else {
+        record_ports(label)
+    }
+}
+
+fn record_ports(label: &str) -> Vec<String> {
+    let mut ports = Vec::new();
+    let mut chars = label.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '<' => {
+                let mut port = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => port.extend(chars.next()),
+                        '>' => break,
+                        c => port.push(c),
+                    }
+                }
+                ports.push(port.trim().to_string());
+            }
+            _ => {}
+        }
+    }
+    ports
+}
+
+fn html_ports(markup: &str) -> Vec<String> {
+    let upper = markup.to_ascii_uppercase();
+    let mut ports = Vec::new();
+    let mut rest = 0;
+    while let Some(found) = upper[rest..].find("PORT") {
+        let start = rest + found + "PORT".len();
+        rest = start;
+        // only `PORT = "..."`, not e.g. `<TD>PORTS</TD>`
+        let Some(value) = markup[start..].trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        if let Some(end) = value[1..].find(quote) {
+            ports.push(unescape_html(&value[1..end + 1]));
+        }
+    }
+    ports
+}
+
+fn unescape_html(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_round_trip() {
+        for (text, port) in [
+            ("in", Port::named("in")),
+            ("ne", Port::compass(CompassPoint::NE)),
+            ("in:s", Port::named("in").at(CompassPoint::S)),
+            ("_", Port::compass(CompassPoint::Any)),
+        ] {
+            assert_eq!(text.parse::<Port>().unwrap(), port);
+            assert_eq!(port.to_string(), text);
+        }
+        assert!("in:up".parse::<Port>().is_err());
+        assert!(":n".parse::<Port>().is_err());
+    }
+
+    #[test]
+    fn test_label_ports() {
+        assert_eq!(label_ports("{<in> a | {<x>b|c}} | <out> \\<d\\>", false), vec!["in", "x", "out"]);
+        assert!(label_ports("plain", false).is_empty());
+        assert_eq!(
+            label_ports("<TABLE><TR><TD PORT=\"a&amp;b\">x</TD><TD port='c'>PORTS</TD></TR></TABLE>", true),
+            vec!["a&b", "c"]
+        );
+    }
+}
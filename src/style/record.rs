@@ -0,0 +1,127 @@
+//! Builder for the labels of `record` and `Mrecord` shaped nodes.
+//!
+//! A record label is a list of fields separated by `|`. Fields can carry a port name edges
+//! attach to, and a nested list in braces flips the direction the fields are stacked in.
+//!
+//! ```rust
+//! use wrappedviz::style::record::RecordLabel;
+//!
+//! let label = RecordLabel::new()
+//!     .port_field("in", "input")
+//!     .nested(RecordLabel::new().field("a|b").field("c"))
+//!     .port_field("out", "output");
+//! assert_eq!(label.to_string(), "<in> input | {a\\|b | c} | <out> output");
+//! assert_eq!(label.ports(), vec!["in", "out"]);
+//! ```
+//!
+//! The syntax is described at <https://graphviz.org/doc/info/shapes.html#record>.
+
+use crate::style::CommonAttr;
+use std::fmt;
+
+/// Escapes the characters that have a meaning in record labels so `text` shows up as is.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '\\' | '"') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// one field of a [`RecordLabel`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    /// a text field, optionally named as a port
+    Text { port: Option<String>, text: String },
+    /// fields stacked in the other direction
+    Nested(RecordLabel),
+}
+
+/// The label of a record shaped node.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RecordLabel {
+    fields: Vec<Field>,
+}
+
+impl RecordLabel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// appends a text field
+    pub fn field(mut self, text: &str) -> Self {
+        self.fields.push(Field::Text { port: None, text: text.to_string() });
+        self
+    }
+
+    /// appends a text field edges can attach to with the port `port`
+    pub fn port_field(mut self, port: &str, text: &str) -> Self {
+        self.fields.push(Field::Text { port: Some(port.to_string()), text: text.to_string() });
+        self
+    }
+
+    /// appends fields stacked in the other direction, `{...}`
+    pub fn nested(mut self, record: RecordLabel) -> Self {
+        self.fields.push(Field::Nested(record));
+        self
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// the port names of all fields, nested ones included, in label order
+    pub fn ports(&self) -> Vec<&str> {
+        let mut ports = Vec::new();
+        for field in &self.fields {
+            match field {
+                Field::Text { port: Some(port), .. } => ports.push(port.as_str()),
+                Field::Text { port: None, .. } => {}
+                Field::Nested(record) => ports.extend(record.ports()),
+            }
+        }
+        ports
+    }
+}
+
+impl fmt::Display for RecordLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            match field {
+                Field::Text { port: Some(port), text } => write!(f, "<{}> {}", escape(port), escape(text))?,
+                Field::Text { port: None, text } => write!(f, "{}", escape(text))?,
+                Field::Nested(record) => write!(f, "{{{}}}", record)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<RecordLabel> for CommonAttr {
+    fn from(record: RecordLabel) -> Self {
+        CommonAttr::Label(record.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::label_ports;
+
+    #[test]
+    fn test_record_label() {
+        let label = RecordLabel::new()
+            .field("{x}")
+            .nested(RecordLabel::new().port_field("p", "a").nested(RecordLabel::new().port_field("q", "b")));
+        assert_eq!(label.to_string(), "\\{x\\} | {<p> a | {<q> b}}");
+        assert_eq!(label.ports(), vec!["p", "q"]);
+        assert_eq!(label_ports(&label.to_string(), false), vec!["p", "q"]);
+        assert_eq!(CommonAttr::from(label.clone()), CommonAttr::Label(label.to_string()));
+    }
+}
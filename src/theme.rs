@@ -1,4 +1,17 @@
 
+/// A layout engine plus default node and edge attributes, applied with
+/// [`cgraph::Graph::apply_theme`](crate::cgraph::Graph::apply_theme) or
+/// [`RustGraph::apply_theme`](crate::rgraph::RustGraph::apply_theme).
+///
+/// With the `theme-files` feature themes can be loaded from JSON or TOML, attributes are written
+/// the way they appear in DOT:
+///
+/// ```toml
+/// name = "boxes"
+/// layout = "Dot"
+/// node_attrs = ['shape="box"']
+/// edge_attrs = ['color="#ff0000"']
+/// ```
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(
     feature = "serde",
@@ -22,4 +35,67 @@ impl Default for GraphTheme {
             edge_attrs: Vec::new(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "theme-files")]
+impl GraphTheme {
+    /// Reads a theme from JSON.
+    ///
+    /// # Errors
+    /// Returns [`Error::ParseFailed`](crate::Error::ParseFailed) if `json` is not a valid theme.
+    pub fn from_json(json: &str) -> Result<Self, crate::Error> {
+        serde_json::from_str(json).map_err(|e| crate::Error::ParseFailed(format!("theme: {}", e)))
+    }
+
+    /// Reads a theme from TOML.
+    ///
+    /// # Errors
+    /// Returns [`Error::ParseFailed`](crate::Error::ParseFailed) if `toml` is not a valid theme.
+    pub fn from_toml(toml: &str) -> Result<Self, crate::Error> {
+        toml::from_str(toml).map_err(|e| crate::Error::ParseFailed(format!("theme: {}", e)))
+    }
+
+    /// Reads a theme file, as TOML if its extension is `.toml` and as JSON otherwise.
+    ///
+    /// # Errors
+    /// - [`Error::Io`](crate::Error::Io) if the file can't be read.
+    /// - [`Error::ParseFailed`](crate::Error::ParseFailed) if it is not a valid theme.
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, crate::Error> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            _ => Self::from_json(&content),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "theme-files"))]
+mod tests {
+    use super::*;
+    use crate::{EdgeAttr, Layout, NodeAttr};
+
+    #[test]
+    fn test_theme_from_json_and_toml() {
+        let json = r##"{
+            "name": "boxes",
+            "layout": "Neato",
+            "node_attrs": ["shape=\"box\""],
+            "edge_attrs": ["color=\"#ff0000\""]
+        }"##;
+        let theme = GraphTheme::from_json(json).unwrap();
+        assert_eq!(theme.layout, Layout::Neato);
+        assert!(matches!(theme.node_attrs[..], [NodeAttr::Shape(_)]));
+        assert!(matches!(theme.edge_attrs[..], [EdgeAttr::Color(_)]));
+
+        let toml = r##"
+            name = "boxes"
+            layout = "Neato"
+            node_attrs = ['shape="box"']
+            edge_attrs = ['color="#ff0000"']
+        "##;
+        assert_eq!(GraphTheme::from_toml(toml).unwrap(), theme);
+
+        assert!(matches!(GraphTheme::from_json("{}"), Err(crate::Error::ParseFailed(_))));
+    }
+}
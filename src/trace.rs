@@ -0,0 +1,20 @@
+//! Internal logging facade.
+//!
+//! With the `tracing` feature enabled these forward to the `tracing` crate, otherwise they
+//! expand to nothing, so the library never writes to stdout on its own.
+
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::{debug, trace};
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug {
+    ($($arg:tt)*) => {};
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use {debug, trace};
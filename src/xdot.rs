@@ -0,0 +1,306 @@
+//! Parser for the drawing operations graphviz emits in the `xdot` output formats.
+//!
+//! Rendering with [`OutputFormat::Xdot`](crate::OutputFormat::Xdot) (or `Xdot12`, `Xdot14`, `XdotJson`)
+//! annotates every graph, node and edge with `_draw_`, `_ldraw_`, `_hdraw_`, `_tdraw_`, `_hldraw_`
+//! and `_tldraw_` attributes. Their values are sequences of drawing operations which [`parse`]
+//! turns into [`DrawOp`]s, so a client can paint a layout without going through SVG.
+//!
+//! ```rust
+//! use wrappedviz::xdot::{self, DrawOp};
+//!
+//! let ops = xdot::parse("c 7 -#000000 e 27 18 27 18 ").unwrap();
+//! assert!(matches!(ops[1], DrawOp::Ellipse { filled: false, .. }));
+//! ```
+//!
+//! The format is described at <https://graphviz.org/docs/outputs/canon/#xdot>.
+
+use crate::Error;
+use crate::layout::Point;
+
+/// Horizontal alignment of a text operation relative to its anchor point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// Which color a color operation sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum ColorTarget {
+    /// `C`, used to fill closed shapes
+    Fill,
+    /// `c`, used for outlines, lines and text
+    Pen,
+}
+
+/// A single xdot drawing operation.
+///
+/// Colors, fonts and styles are state changes that apply to all following shapes.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum DrawOp {
+    /// `E` / `e`: an ellipse given by its center and its horizontal and vertical radii
+    Ellipse { center: Point, rx: f64, ry: f64, filled: bool },
+    /// `P` / `p`: a closed polygon
+    Polygon { points: Vec<Point>, filled: bool },
+    /// `L`: an open polyline
+    Polyline { points: Vec<Point> },
+    /// `B` / `b`: a piecewise cubic bezier curve with `3n + 1` control points
+    Bezier { points: Vec<Point>, filled: bool },
+    /// `T`: text anchored at `position`, `width` is the width graphviz estimated for it
+    Text { position: Point, align: TextAlign, width: f64, text: String },
+    /// `C` / `c`: a color name, `#rrggbb[aa]` value or, since xdot 1.2, a gradient
+    Color { target: ColorTarget, color: String },
+    /// `F`: font size in points and font name
+    Font { size: f64, name: String },
+    /// `S`: a style such as `dashed`, `bold` or `setlinewidth(2)`
+    Style(String),
+    /// `t`: font characteristics as a bit set, see [`DrawOp::BOLD`] and friends
+    FontFlags(u32),
+    /// `I`: an image placed in the box with lower left corner `position`
+    Image { position: Point, width: f64, height: f64, name: String },
+}
+
+impl DrawOp {
+    pub const BOLD: u32 = 1;
+    pub const ITALIC: u32 = 2;
+    pub const UNDERLINE: u32 = 4;
+    pub const SUPERSCRIPT: u32 = 8;
+    pub const SUBSCRIPT: u32 = 16;
+    pub const STRIKE_THROUGH: u32 = 32;
+    pub const OVERLINE: u32 = 64;
+}
+
+/// Parses an xdot attribute value, e.g. the contents of `_draw_`, into its drawing operations.
+///
+/// # Errors
+/// Returns [`Error::ParseFailed`] naming the byte offset of the first malformed operation.
+pub fn parse(input: &str) -> Result<Vec<DrawOp>, Error> {
+    let mut parser = Parser { input, pos: 0 };
+    let mut ops = Vec::new();
+    while let Some(op) = parser.next_op()? {
+        ops.push(op);
+    }
+    Ok(ops)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next_op(&mut self) -> Result<Option<DrawOp>, Error> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let Some(code) = self.input[self.pos..].chars().next() else {
+            return Ok(None);
+        };
+        self.pos += code.len_utf8();
+
+        let op = match code {
+            'E' | 'e' => DrawOp::Ellipse {
+                center: self.point()?,
+                rx: self.number()?,
+                ry: self.number()?,
+                filled: code == 'E',
+            },
+            'P' | 'p' => DrawOp::Polygon {
+                points: self.points()?,
+                filled: code == 'P',
+            },
+            'L' => DrawOp::Polyline { points: self.points()? },
+            'B' | 'b' => DrawOp::Bezier {
+                points: self.points()?,
+                filled: code == 'b',
+            },
+            'T' => {
+                let position = self.point()?;
+                let align = match self.integer()? {
+                    -1 => TextAlign::Left,
+                    0 => TextAlign::Center,
+                    1 => TextAlign::Right,
+                    other => return Err(self.error(&format!("invalid text alignment {}", other))),
+                };
+                DrawOp::Text {
+                    position,
+                    align,
+                    width: self.number()?,
+                    text: self.bytes()?,
+                }
+            }
+            'C' | 'c' => DrawOp::Color {
+                target: if code == 'C' { ColorTarget::Fill } else { ColorTarget::Pen },
+                color: self.bytes()?,
+            },
+            'F' => DrawOp::Font {
+                size: self.number()?,
+                name: self.bytes()?,
+            },
+            'S' => DrawOp::Style(self.bytes()?),
+            't' => {
+                let flags = self.integer()?;
+                let flags = u32::try_from(flags)
+                    .map_err(|_| self.error(&format!("invalid font flags {}", flags)))?;
+                DrawOp::FontFlags(flags)
+            }
+            'I' => DrawOp::Image {
+                position: self.point()?,
+                width: self.number()?,
+                height: self.number()?,
+                name: self.bytes()?,
+            },
+            other => {
+                self.pos = start;
+                return Err(self.error(&format!("unknown operation '{}'", other)));
+            }
+        };
+        Ok(Some(op))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn token(&mut self) -> Result<&'a str, Error> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("unexpected end of input"));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn number(&mut self) -> Result<f64, Error> {
+        let token = self.token()?;
+        token
+            .parse()
+            .map_err(|_| self.error(&format!("expected a number, found '{}'", token)))
+    }
+
+    fn integer(&mut self) -> Result<i64, Error> {
+        let token = self.token()?;
+        token
+            .parse()
+            .map_err(|_| self.error(&format!("expected an integer, found '{}'", token)))
+    }
+
+    fn point(&mut self) -> Result<Point, Error> {
+        Ok(Point::new(self.number()?, self.number()?))
+    }
+
+    fn points(&mut self) -> Result<Vec<Point>, Error> {
+        let count = self.integer()?;
+        let count = usize::try_from(count)
+            .map_err(|_| self.error(&format!("invalid point count {}", count)))?;
+        (0..count).map(|_| self.point()).collect()
+    }
+
+    /// reads a `n -bytes` field, a byte count followed by a dash and exactly that many bytes
+    fn bytes(&mut self) -> Result<String, Error> {
+        let count = self.integer()?;
+        let count = usize::try_from(count)
+            .map_err(|_| self.error(&format!("invalid byte count {}", count)))?;
+        self.skip_whitespace();
+        if !self.input[self.pos..].starts_with('-') {
+            return Err(self.error("expected '-' before string"));
+        }
+        self.pos += 1;
+        let end = self.pos + count;
+        let text = self
+            .input
+            .get(self.pos..end)
+            .ok_or_else(|| self.error(&format!("string of {} bytes is truncated", count)))?;
+        self.pos = end;
+        Ok(text.to_string())
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        Error::ParseFailed(format!("xdot: {} at byte {}", msg, self.pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_node_draw() {
+        let ops = parse("c 7 -#000000 e 27 18 27 18 ").unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                DrawOp::Color { target: ColorTarget::Pen, color: "#000000".into() },
+                DrawOp::Ellipse { center: Point::new(27.0, 18.0), rx: 27.0, ry: 18.0, filled: false },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_shapes() {
+        let ops = parse("P 4 0 0 54 0 54 36 0 36 L 2 1.5 2 3 4.5 b 4 0 0 1 1 2 2 3 3").unwrap();
+        assert_eq!(ops.len(), 3);
+        match &ops[0] {
+            DrawOp::Polygon { points, filled } => {
+                assert!(*filled);
+                assert_eq!(points.len(), 4);
+                assert_eq!(points[2], Point::new(54.0, 36.0));
+            }
+            other => panic!("unexpected op {:?}", other),
+        }
+        assert_eq!(
+            ops[1],
+            DrawOp::Polyline { points: vec![Point::new(1.5, 2.0), Point::new(3.0, 4.5)] }
+        );
+        assert!(matches!(&ops[2], DrawOp::Bezier { points, filled: true } if points.len() == 4));
+    }
+
+    #[test]
+    fn test_parse_text_and_font() {
+        let ops = parse("F 14 11 -Times-Roman t 3 T 27 13.8 0 7.77 6 -A -> B S 15 -setlinewidth(2)").unwrap();
+        assert_eq!(ops[0], DrawOp::Font { size: 14.0, name: "Times-Roman".into() });
+        assert_eq!(ops[1], DrawOp::FontFlags(DrawOp::BOLD | DrawOp::ITALIC));
+        assert_eq!(
+            ops[2],
+            DrawOp::Text {
+                position: Point::new(27.0, 13.8),
+                align: TextAlign::Center,
+                width: 7.77,
+                text: "A -> B".into(),
+            }
+        );
+        assert_eq!(ops[3], DrawOp::Style("setlinewidth(2)".into()));
+    }
+
+    #[test]
+    fn test_byte_counts_are_utf8_bytes() {
+        let ops = parse("T 0 0 -1 10 4 -héj I 0 0 10 20 7 -img.png").unwrap();
+        assert!(matches!(&ops[0], DrawOp::Text { text, align: TextAlign::Left, .. } if text == "héj"));
+        assert!(matches!(&ops[1], DrawOp::Image { name, height, .. } if name == "img.png" && *height == 20.0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("").unwrap().is_empty());
+        assert!(matches!(parse("X 1 2"), Err(Error::ParseFailed(_))));
+        assert!(matches!(parse("e 1 2 3"), Err(Error::ParseFailed(_))));
+        assert!(matches!(parse("P 2 0 0 1"), Err(Error::ParseFailed(_))));
+        assert!(matches!(parse("c 20 -#fff"), Err(Error::ParseFailed(_))));
+        assert!(matches!(parse("T 0 0 2 10 1 -a"), Err(Error::ParseFailed(_))));
+    }
+}
@@ -0,0 +1,12 @@
+#!/usr/bin/env bash
+# needs valgrind and graphviz, runs the tests that create and free graphviz objects under memcheck
+# and fails on invalid reads and writes or definitely lost memory
+set -e
+test_bin=$(cargo test --lib --no-run 2>&1 | grep -o 'target/debug/deps/wrappedviz-[0-9a-f]*' | head -n 1)
+valgrind --leak-check=full --errors-for-leak-kinds=definite --error-exitcode=1 \
+    "$test_bin" --test-threads=1 \
+    test_memory_cycles \
+    test_relayout_and_drop_order \
+    test_remove_and_recreate_cluster \
+    test_parsed_nested_clusters \
+    test_custom_renderer